@@ -1,6 +1,6 @@
 use cliffy::Vec3;
 
-use crate::ray::Ray;
+use crate::{mat4::Mat4, ray::Ray};
 
 #[derive(Default, Clone)]
 pub struct AABB {
@@ -28,6 +28,32 @@ impl AABB {
         Self { min, max }
     }
 
+    // The box around this one after going through `m`.
+    pub fn transformed(&self, m: &Mat4) -> Self {
+        let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vec3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY);
+
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            let p = m.transform_point(&corner);
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+
+        Self { min, max }
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn hit(&self, r: &Ray, mut t_min: f32, mut t_max: f32) -> bool {
         for a in 0..3 {
             let inv_d = 1.0 / r.direction[a];
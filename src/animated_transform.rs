@@ -0,0 +1,154 @@
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    mat4::Mat4,
+    quaternion::{self, Quaternion},
+    ray::Ray,
+    sampler::Sampler,
+    transform::{hit_to_world, ray_to_local},
+};
+use cliffy::{Vec3, Vector};
+use std::sync::Arc;
+
+// Each stretch between two keyframes is sampled this many times over to find
+// the space the object sweeps through.
+const BOUNDS_STEPS: usize = 16;
+
+// Where an object is at `time`: scaled, then rotated, then translated. The parts
+// are kept apart so each can be interpolated in the way that suits it.
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_trs(&self.translation, self.rotation, &self.scale)
+    }
+
+    // Translation and scale move in a straight line, the rotation turns at a
+    // constant rate.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            time: self.time + t * (other.time - self.time),
+            translation: self.translation + t * (other.translation - self.translation),
+            rotation: quaternion::slerp(self.rotation, other.rotation, t),
+            scale: self.scale + t * (other.scale - self.scale),
+        }
+    }
+}
+
+// An object moving through a series of keyframes over the shutter. It stays put
+// at the first keyframe before it and at the last one after it.
+pub struct AnimatedTransform {
+    pub ptr: Arc<Hittable>,
+    // In order of time.
+    pub keyframes: Vec<Keyframe>,
+    pub bbox: Option<AABB>,
+}
+
+impl AnimatedTransform {
+    pub fn new(ptr: Arc<Hittable>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "an animation needs a keyframe");
+        keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+
+        let bbox = ptr
+            .bounding_box(0.0, 1.0)
+            .map(|bbox| motion_bounds(&bbox, &keyframes));
+
+        Self {
+            ptr,
+            keyframes,
+            bbox,
+        }
+    }
+
+    pub fn at(&self, time: f32) -> Keyframe {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        match next {
+            Some(0) => self.keyframes[0],
+            Some(i) => {
+                let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                a.lerp(b, (time - a.time) / (b.time - a.time))
+            }
+            None => self.keyframes[self.keyframes.len() - 1],
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let matrix = self.at(r.time).matrix();
+        // The scale passes through zero, so there's nothing to hit right then.
+        let inverse = matrix.inverse()?;
+        let local_r = ray_to_local(&inverse, r);
+
+        if let Some(mut rec) = self.ptr.hit(&local_r, t_min, t_max, sampler) {
+            hit_to_world(&matrix, &inverse, &mut rec);
+            Some(rec)
+        } else {
+            None
+        }
+    }
+
+    pub fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        self.bbox.clone()
+    }
+}
+
+// A box around everywhere `bbox` goes between the first and the last keyframe.
+// Corners move along curves when rotating, so a box around samples of the motion
+// could cut them short. Growing it by how far a corner can get between two
+// samples makes sure it doesn't.
+fn motion_bounds(bbox: &AABB, keyframes: &[Keyframe]) -> AABB {
+    let mut result = bbox.transformed(&keyframes[0].matrix());
+
+    for pair in keyframes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+
+        // How fast a corner can move, as a point at `p` goes to
+        // T + R S p: its translation, its rotation at its farthest from the
+        // origin, and its scaling all add up.
+        let angle = quaternion::angle_between(a.rotation, b.rotation);
+        let speed = corners(bbox)
+            .iter()
+            .map(|p| {
+                let farthest = mul(&a.scale, p).mag().max(mul(&b.scale, p).mag());
+                (b.translation - a.translation).mag()
+                    + angle * farthest
+                    + mul(&(b.scale - a.scale), p).mag()
+            })
+            .fold(0.0, f32::max);
+
+        let mut swept = bbox.transformed(&a.matrix());
+        for i in 1..=BOUNDS_STEPS {
+            let k = a.lerp(b, i as f32 / BOUNDS_STEPS as f32);
+            swept = AABB::surrounding_box(swept, bbox.transformed(&k.matrix()));
+        }
+
+        // No point is further than half a step from one of the samples.
+        let pad = Vec3::uni(speed / (2 * BOUNDS_STEPS) as f32);
+        swept = AABB::new(&(swept.min - pad), &(swept.max + pad));
+
+        result = AABB::surrounding_box(result, swept);
+    }
+
+    result
+}
+
+fn corners(bbox: &AABB) -> Vec<Vec3> {
+    (0..8)
+        .map(|i| {
+            Vec3::new(
+                if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if i & 4 == 0 { bbox.min.z } else { bbox.max.z },
+            )
+        })
+        .collect()
+}
+
+fn mul(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x * b.x, a.y * b.y, a.z * b.z)
+}
@@ -0,0 +1,114 @@
+use crate::{
+    animated_transform::AnimatedTransform, camera::Camera, constant_medium::ConstantMedium,
+    hittable::Hittable, hittable_list::HittableList, instance::Instance, rotate::RotateY,
+    sampler::Sampler, transform::Transform, translate::Translate,
+};
+use std::{sync::Arc, time::Instant};
+
+// Each way through the scene is timed this many times and the fastest run kept.
+const ROUNDS: usize = 3;
+
+// Times camera rays through the world as it is rendered, with flattened BVHs,
+// against the same world with the pointer trees they were flattened from.
+pub fn bvh(world: &HittableList, cam: &Camera, width: u32, height: u32, seed: u64) {
+    let tree = HittableList {
+        objects: world.objects.iter().map(as_tree).collect(),
+    };
+
+    let (linear_time, linear_hits) = trace(world, cam, width, height, seed);
+    let (tree_time, tree_hits) = trace(&tree, cam, width, height, seed);
+
+    let rays = (width * height) as f64;
+    println!(
+        "Tree BVH:   {:8.2} ms, {:6.2} Mrays/s",
+        tree_time * 1000.0,
+        rays / tree_time / 1e6
+    );
+    println!(
+        "Linear BVH: {:8.2} ms, {:6.2} Mrays/s",
+        linear_time * 1000.0,
+        rays / linear_time / 1e6
+    );
+    println!("Speedup: {:.2}x", tree_time / linear_time);
+
+    if tree_hits != linear_hits {
+        println!(
+            "The BVHs disagree: {} hits with the tree, {} with the linear one.",
+            tree_hits, linear_hits
+        );
+    }
+}
+
+fn trace(world: &HittableList, cam: &Camera, width: u32, height: u32, seed: u64) -> (f64, usize) {
+    let mut best = f64::INFINITY;
+    let mut hits = 0;
+
+    for _ in 0..ROUNDS {
+        let mut sampler = Sampler::new(seed);
+        let start = Instant::now();
+
+        hits = 0;
+        for j in 0..height {
+            for i in 0..width {
+                let u = (i as f32 + sampler.random_float()) / (width - 1) as f32;
+                let v = (j as f32 + sampler.random_float()) / (height - 1) as f32;
+                let r = cam.get_ray(u, v, &mut sampler);
+                if world.hit(&r, 0.001, f32::INFINITY, &mut sampler).is_some() {
+                    hits += 1;
+                }
+            }
+        }
+
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+
+    (best, hits)
+}
+
+// Rebuilds `object` with every flattened BVH in it turned back into a tree.
+// Meshes keep theirs, as they can only hold the flattened kind.
+fn as_tree(object: &Arc<Hittable>) -> Arc<Hittable> {
+    let tree = match &**object {
+        Hittable::Bvh(bvh) => Hittable::Node(bvh.to_tree(&as_tree)),
+        Hittable::List(list) => Hittable::List(HittableList {
+            objects: list.objects.iter().map(as_tree).collect(),
+        }),
+        Hittable::Translate(t) => Hittable::Translate(Translate {
+            ptr: as_tree(&t.ptr),
+            offset: t.offset,
+        }),
+        Hittable::RotateY(r) => Hittable::RotateY(RotateY {
+            ptr: as_tree(&r.ptr),
+            angle: r.angle,
+            sin_theta: r.sin_theta,
+            cos_theta: r.cos_theta,
+            bbox: r.bbox.clone(),
+        }),
+        Hittable::Instance(inst) => Hittable::Instance(Instance {
+            object: as_tree(&inst.object),
+            transform: inst.transform,
+            inverse: inst.inverse,
+            material: inst.material.clone(),
+            bbox: inst.bbox.clone(),
+        }),
+        Hittable::Transform(t) => Hittable::Transform(Transform {
+            ptr: as_tree(&t.ptr),
+            matrix: t.matrix,
+            inverse: t.inverse,
+            bbox: t.bbox.clone(),
+        }),
+        Hittable::AnimatedTransform(t) => Hittable::AnimatedTransform(AnimatedTransform {
+            ptr: as_tree(&t.ptr),
+            keyframes: t.keyframes.clone(),
+            bbox: t.bbox.clone(),
+        }),
+        Hittable::ConstantMedium(m) => Hittable::ConstantMedium(ConstantMedium {
+            boundary: as_tree(&m.boundary),
+            phase_function: m.phase_function.clone(),
+            neg_inv_density: m.neg_inv_density,
+        }),
+        _ => return object.clone(),
+    };
+
+    Arc::new(tree)
+}
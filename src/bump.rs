@@ -0,0 +1,146 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    medium::Medium,
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+    utilities,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::{any::Any, sync::Arc};
+
+// Step in texture coordinates over which a height map's slope is measured.
+const DELTA: f32 = 0.0005;
+
+pub enum BumpMap {
+    // A tangent-space normal map, x along u, y along v and z out of the surface,
+    // each stored from 0 to 1 as usual. The second value scales how far the
+    // normals lean.
+    Normal(Arc<Texture>, f32),
+    // Heights, the average of the channels times the second value, in scene
+    // units. Only the normal follows them, the surface itself stays where it is.
+    Height(Arc<Texture>, f32),
+}
+
+// Any material with detail too fine to model, like grooves, scratches or
+// pores, added by tilting the normal it shades with. Rays still leave from the
+// actual surface, and which side of it they go is still up to its true normal.
+pub struct Bumped {
+    pub base: Arc<dyn Material>,
+    pub map: BumpMap,
+}
+
+impl Bumped {
+    pub fn new(base: Arc<dyn Material>, map: BumpMap) -> Self {
+        Self { base, map }
+    }
+
+    // `rec` as the base material gets to see it.
+    fn perturbed(&self, r_in: &Ray, rec: &HitRecord) -> HitRecord {
+        // Work on the outside, where maps are made for.
+        let side = if rec.front_face { 1.0 } else { -1.0 };
+        let n = side * rec.normal;
+        let (dpdu, dpdv) = tangents(rec, &n);
+
+        let normal = match &self.map {
+            BumpMap::Normal(texture, strength) => {
+                let c = texture.value_at(rec);
+                let t = (dpdu - n.dot(dpdu) * n).normalized();
+                let mut b = n.cross(t);
+                if b.dot(dpdv) < 0.0 {
+                    b = -b;
+                }
+
+                let x = (2.0 * c.r - 1.0) * strength;
+                let y = (2.0 * c.g - 1.0) * strength;
+                let z = (2.0 * c.b - 1.0).max(0.0);
+                x * t + y * b + z * n
+            }
+            BumpMap::Height(texture, scale) => {
+                let height = |uv: &Vec2, p: &Vec3| {
+                    let c = texture.value(uv, p);
+                    (c.r + c.g + c.b) / 3.0 * scale
+                };
+
+                let h = height(&rec.uv, &rec.point);
+                let du = height(
+                    &Vec2::new(rec.uv.x + DELTA, rec.uv.y),
+                    &(rec.point + DELTA * dpdu),
+                ) - h;
+                let dv = height(
+                    &Vec2::new(rec.uv.x, rec.uv.y + DELTA),
+                    &(rec.point + DELTA * dpdv),
+                ) - h;
+
+                let m = (dpdu + du / DELTA * n).cross(dpdv + dv / DELTA * n);
+                if m.dot(n) < 0.0 {
+                    -m
+                } else {
+                    m
+                }
+            }
+        };
+
+        let mut normal = if normal.mag_sq() > 0.0 {
+            side * normal.normalized()
+        } else {
+            rec.normal
+        };
+
+        // A normal tilted away from where the ray came from would leave the
+        // material nothing to reflect, so it's tilted back just far enough.
+        let wo = -r_in.direction.normalized();
+        let cos = wo.dot(normal);
+        if cos < 0.01 {
+            normal = (normal + (0.01 - cos) * wo).normalized();
+        }
+
+        let mut perturbed = rec.clone();
+        perturbed.normal = normal;
+        perturbed
+    }
+}
+
+// Tangents along u and v at `rec`, made up around `n` for shapes that don't
+// have any.
+fn tangents(rec: &HitRecord, n: &Vec3) -> (Vec3, Vec3) {
+    let along = |v: &Vec3| v.cross(*n).mag_sq() > 1e-12 * v.mag_sq();
+    match (along(&rec.dpdu), along(&rec.dpdv)) {
+        (true, true) => (rec.dpdu, rec.dpdv),
+        (true, false) => (rec.dpdu, n.cross(rec.dpdu)),
+        (false, true) => (rec.dpdv.cross(*n), rec.dpdv),
+        (false, false) => utilities::orthonormal_basis(n),
+    }
+}
+
+impl Material for Bumped {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        self.base.eval(r_in, &self.perturbed(r_in, rec), direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        self.base.pdf(r_in, &self.perturbed(r_in, rec), direction)
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        self.base.sample(r_in, &self.perturbed(r_in, rec), sampler)
+    }
+
+    fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
+        self.base.emitted(uv, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.base.is_specular()
+    }
+
+    fn medium(&self, rec: &HitRecord) -> Option<Medium> {
+        self.base.medium(rec)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -2,106 +2,144 @@ use cliffy::Vec3;
 
 use crate::{
     aabb::AABB,
-    dielectric::Dielectric,
     hittable::{HitRecord, Hittable},
     hittable_list::HittableList,
     ray::Ray,
-    sphere::Sphere,
-    utilities,
+    sampler::Sampler,
 };
-use std::{cmp::Ordering, rc::Rc};
+use std::sync::Arc;
+
+// Leaves hold up to this many objects unless told otherwise.
+pub const MAX_LEAF_SIZE: usize = 4;
+
+// The surface area heuristic weighs the cost of visiting a node against that of
+// intersecting an object, relative to each other.
+pub const TRAVERSAL_COST: f32 = 0.125;
+pub const INTERSECTION_COST: f32 = 1.0;
+
+// Number of buckets the centroids are sorted into along each axis when looking
+// for a split, instead of trying every possible one.
+const BIN_COUNT: usize = 12;
 
 pub struct BVHNode {
-    pub left: Rc<Hittable>,
-    pub right: Rc<Hittable>,
+    pub left: Arc<Hittable>,
+    pub right: Arc<Hittable>,
     pub bbox: AABB,
 }
 
+// What the builder works on: an object along with its bounds, so they are only
+// computed once.
+struct Primitive {
+    object: Arc<Hittable>,
+    bbox: AABB,
+    centroid: Vec3,
+}
+
+struct Split {
+    axis: usize,
+    bin: usize,
+    cost: f32,
+}
+
 impl BVHNode {
     pub fn new(list: &mut HittableList, time0: f32, time1: f32) -> Self {
-        let end = list.objects.len();
-        Self::new2(&mut list.objects, 0, end, time0, time1)
+        Self::with_leaf_size(list, time0, time1, MAX_LEAF_SIZE)
     }
 
-    pub fn new2(
-        src_objects: &mut [Rc<Hittable>],
-        start: usize,
-        end: usize,
+    pub fn with_leaf_size(
+        list: &mut HittableList,
         time0: f32,
         time1: f32,
+        max_leaf_size: usize,
     ) -> Self {
-        let mut objects = src_objects;
+        let mut primitives: Vec<Primitive> = list
+            .objects
+            .iter()
+            .map(|object| {
+                let bbox = object.bounding_box(time0, time1).unwrap_or_else(|| {
+                    eprintln!("Warning: an object without a bounding box was put in a BVH.");
+                    Default::default()
+                });
+                let centroid = 0.5 * (bbox.min + bbox.max);
+                Primitive {
+                    object: object.clone(),
+                    bbox,
+                    centroid,
+                }
+            })
+            .collect();
 
-        let axis = utilities::random_int(0, 2);
-        let comparator = match axis {
-            0 => box_x_compare,
-            1 => box_y_compare,
-            _ => box_z_compare,
-        };
+        // Keep the list in the order the tree ended up with, like sorting it
+        // used to.
+        let node = Self::new2(&mut primitives, max_leaf_size.max(1));
+        list.objects = primitives.into_iter().map(|p| p.object).collect();
+        node
+    }
 
-        let object_span = end - start;
+    // Always splits, unless there is only one object to begin with.
+    fn new2(primitives: &mut [Primitive], max_leaf_size: usize) -> Self {
+        let bbox = bounds(primitives);
 
-        let left;
-        let right;
-        match object_span {
-            1 => {
-                left = objects[start].clone();
-                right = objects[start].clone();
-            }
-            2 => {
-                if comparator(&objects[start], &objects[start + 1]) == Ordering::Less {
-                    left = objects[start].clone();
-                    right = objects[start + 1].clone();
-                } else {
-                    left = objects[start + 1].clone();
-                    right = objects[start].clone();
-                }
-            }
-            _ => {
-                objects.sort_by(comparator);
-
-                let mid = start + object_span / 2;
-                left = Rc::new(Hittable::Node(BVHNode::new2(
-                    objects, start, mid, time0, time1,
-                )));
-                right = Rc::new(Hittable::Node(BVHNode::new2(
-                    objects, mid, end, time0, time1,
-                )));
-            }
+        if primitives.len() == 1 {
+            return Self {
+                left: primitives[0].object.clone(),
+                right: primitives[0].object.clone(),
+                bbox,
+            };
+        }
+
+        let mid = match best_split(primitives, &bbox) {
+            Some(split) => partition(primitives, &split),
+            None => 0,
+        };
+        // Everything landed on one side of the split, or all the centroids are
+        // in the same spot. Any split is as good as another then.
+        let mid = if mid == 0 || mid == primitives.len() {
+            primitives.len() / 2
+        } else {
+            mid
         };
 
-        let mut bbox = Default::default();
+        let (left, right) = primitives.split_at_mut(mid);
+        Self {
+            left: Self::child(left, max_leaf_size),
+            right: Self::child(right, max_leaf_size),
+            bbox,
+        }
+    }
 
-        if let Some(box_left) = left.bounding_box(time0, time1) {
-            if let Some(box_right) = right.bounding_box(time0, time1) {
-                bbox = AABB::surrounding_box(box_left, box_right);
-            } else {
-                println!("No bounding box in bvh_node constructor.");
+    fn child(primitives: &mut [Primitive], max_leaf_size: usize) -> Arc<Hittable> {
+        if primitives.len() == 1 {
+            return primitives[0].object.clone();
+        }
+
+        if primitives.len() <= max_leaf_size {
+            let bbox = bounds(primitives);
+            let leaf_cost = primitives.len() as f32 * INTERSECTION_COST;
+            let split_cost = best_split(primitives, &bbox).map_or(f32::INFINITY, |s| s.cost);
+            if leaf_cost <= split_cost {
+                return Arc::new(Hittable::List(HittableList {
+                    objects: primitives.iter().map(|p| p.object.clone()).collect(),
+                }));
             }
-        } else {
-            println!("No bounding box in bvh_node constructor.");
-        };
+        }
 
-        Self { left, right, bbox }
+        Arc::new(Hittable::Node(Self::new2(primitives, max_leaf_size)))
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         if !self.bbox.hit(r, t_min, t_max) {
-            // println!("Miss");
             return None;
         }
 
-        if let Some(l_rec) = self.left.hit(r, t_min, t_max) {
-            if let Some(r_rec) = self.right.hit(r, t_min, l_rec.t) {
-                // println!("Hit");
+        if let Some(l_rec) = self.left.hit(r, t_min, t_max, sampler) {
+            if let Some(r_rec) = self.right.hit(r, t_min, l_rec.t, sampler) {
                 return Some(r_rec);
             } else {
-                // println!("Hit");
                 return Some(l_rec);
             }
         } else {
-            if let Some(r_rec) = self.right.hit(r, t_min, t_max) {
-                // println!("Hit");
+            if let Some(r_rec) = self.right.hit(r, t_min, t_max, sampler) {
                 return Some(r_rec);
             } else {
                 return None;
@@ -114,29 +152,114 @@ impl BVHNode {
     }
 }
 
-#[inline]
-fn box_compare(a: &Rc<Hittable>, b: &Rc<Hittable>, axis: usize) -> Ordering {
-    if let Some(box_a) = a.bounding_box(0.0, 0.0) {
-        if let Some(box_b) = b.bounding_box(0.0, 0.0) {
-            return box_a.min[axis].partial_cmp(&box_b.min[axis]).unwrap();
-        } else {
-            println!("No bounding box in bvh_node constructor.");
+fn bounds(primitives: &[Primitive]) -> AABB {
+    primitives
+        .iter()
+        .skip(1)
+        .fold(primitives[0].bbox.clone(), |acc, p| {
+            AABB::surrounding_box(acc, p.bbox.clone())
+        })
+}
+
+fn centroid_bounds(primitives: &[Primitive]) -> (Vec3, Vec3) {
+    primitives.iter().skip(1).fold(
+        (primitives[0].centroid, primitives[0].centroid),
+        |(min, max), p| (min_vec(&min, &p.centroid), max_vec(&max, &p.centroid)),
+    )
+}
+
+fn bin_index(centroid: f32, min: f32, extent: f32) -> usize {
+    let index = ((centroid - min) / extent * BIN_COUNT as f32) as usize;
+    index.min(BIN_COUNT - 1)
+}
+
+// Finds the cheapest way to split the objects in two by binning their centroids
+// along each axis. `None` if the centroids can't be told apart.
+fn best_split(primitives: &[Primitive], bbox: &AABB) -> Option<Split> {
+    let (c_min, c_max) = centroid_bounds(primitives);
+    let parent_area = bbox.surface_area();
+    let mut best: Option<Split> = None;
+
+    for axis in 0..3 {
+        let extent = c_max[axis] - c_min[axis];
+        if extent <= 0.0 {
+            continue;
+        }
+
+        let mut bins: Vec<(Option<AABB>, usize)> = vec![(None, 0); BIN_COUNT];
+        for p in primitives {
+            let bin = &mut bins[bin_index(p.centroid[axis], c_min[axis], extent)];
+            bin.0 = Some(grow(bin.0.take(), &p.bbox));
+            bin.1 += 1;
+        }
+
+        // Sweep from both ends to get the area and count on either side of
+        // each of the planes between the bins.
+        let mut right_area = [0.0; BIN_COUNT];
+        let mut right_count = [0; BIN_COUNT];
+        let mut acc = None;
+        let mut count = 0;
+        for i in (1..BIN_COUNT).rev() {
+            if let Some(b) = &bins[i].0 {
+                acc = Some(grow(acc, b));
+            }
+            count += bins[i].1;
+            right_area[i] = acc.as_ref().map_or(0.0, AABB::surface_area);
+            right_count[i] = count;
         }
-    } else {
-        println!("No bounding box in bvh_node constructor.");
-    };
 
-    Ordering::Equal
+        let mut acc = None;
+        let mut count = 0;
+        for i in 1..BIN_COUNT {
+            if let Some(b) = &bins[i - 1].0 {
+                acc = Some(grow(acc, b));
+            }
+            count += bins[i - 1].1;
+            if count == 0 || right_count[i] == 0 {
+                continue;
+            }
+
+            let left_area = acc.as_ref().map_or(0.0, AABB::surface_area);
+            let cost = TRAVERSAL_COST
+                + INTERSECTION_COST
+                    * (left_area * count as f32 + right_area[i] * right_count[i] as f32)
+                    / parent_area.max(f32::MIN_POSITIVE);
+
+            let better = match &best {
+                Some(b) => cost < b.cost,
+                None => true,
+            };
+            if better {
+                best = Some(Split { axis, bin: i, cost });
+            }
+        }
+    }
+
+    best
+}
+
+// Moves the objects left of the split to the front and returns how many there
+// are. The sort is stable, so builds are reproducible.
+fn partition(primitives: &mut [Primitive], split: &Split) -> usize {
+    let (c_min, c_max) = centroid_bounds(primitives);
+    let (min, extent) = (c_min[split.axis], c_max[split.axis] - c_min[split.axis]);
+    let is_right = |p: &Primitive| bin_index(p.centroid[split.axis], min, extent) >= split.bin;
+
+    primitives.sort_by_key(is_right);
+    primitives.iter().take_while(|p| !is_right(p)).count()
 }
 
-fn box_x_compare(a: &Rc<Hittable>, b: &Rc<Hittable>) -> Ordering {
-    box_compare(a, b, 0)
+fn grow(bbox: Option<AABB>, other: &AABB) -> AABB {
+    match bbox {
+        Some(bbox) => AABB::surrounding_box(bbox, other.clone()),
+        None => other.clone(),
+    }
 }
 
-fn box_y_compare(a: &Rc<Hittable>, b: &Rc<Hittable>) -> Ordering {
-    box_compare(a, b, 1)
+fn min_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z))
 }
 
-fn box_z_compare(a: &Rc<Hittable>, b: &Rc<Hittable>) -> Ordering {
-    box_compare(a, b, 2)
+fn max_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
 }
@@ -1,6 +1,6 @@
 use cliffy::{Vec3, Vector};
 
-use crate::{ray::Ray, utilities};
+use crate::{ray::Ray, sampler::Sampler};
 
 pub struct Camera {
     origin: Vec3,
@@ -79,14 +79,14 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * utilities::random_in_disk();
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut Sampler) -> Ray {
+        let rd = self.lens_radius * sampler.random_in_disk();
         let offset = self.u * rd.x + self.v * rd.y;
 
         Ray::with_time(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            utilities::random_float_between(self.time_0, self.time_1),
+            sampler.random_float_between(self.time_0, self.time_1),
         )
     }
 }
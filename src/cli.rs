@@ -0,0 +1,163 @@
+use crate::{color::Color, scenes};
+use clap::Parser;
+use cliffy::Vec3;
+use image::ImageFormat;
+use std::path::PathBuf;
+
+/// A simple ray tracer.
+///
+/// Every camera and image option defaults to what the chosen scene was set up
+/// with, so only the values that should differ have to be given.
+#[derive(Parser)]
+#[command(name = "parto", version)]
+pub struct Args {
+    /// Built-in scene to render
+    #[arg(short, long, default_value = "final_scene", value_parser = scenes::NAMES)]
+    pub scene: String,
+
+    /// Scene description file, or glTF file, to render instead of a built-in scene
+    #[arg(short = 'i', long, conflicts_with = "scene")]
+    pub scene_file: Option<PathBuf>,
+
+    /// Print the names of the built-in scenes and exit
+    #[arg(long)]
+    pub list_scenes: bool,
+
+    /// Image width in pixels
+    #[arg(short, long)]
+    pub width: Option<u32>,
+
+    /// Image aspect ratio, either as a number or as "W/H" (e.g. 16/9)
+    #[arg(short, long, value_parser = parse_aspect_ratio)]
+    pub aspect_ratio: Option<f32>,
+
+    /// Samples per pixel
+    #[arg(long = "spp", value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples_per_pixel: Option<u32>,
+
+    /// Maximum number of bounces per camera ray
+    #[arg(short = 'd', long, default_value_t = 50)]
+    pub max_depth: u32,
+
+    /// Background color as "R,G,B"
+    #[arg(short, long, value_parser = parse_color)]
+    pub background: Option<Color>,
+
+    /// Camera position as "X,Y,Z"
+    #[arg(long, value_parser = parse_vec3)]
+    pub look_from: Option<Vec3>,
+
+    /// Point the camera looks at as "X,Y,Z"
+    #[arg(long, value_parser = parse_vec3)]
+    pub look_at: Option<Vec3>,
+
+    /// Camera up vector as "X,Y,Z"
+    #[arg(long, value_parser = parse_vec3)]
+    pub vup: Option<Vec3>,
+
+    /// Vertical field of view in degrees
+    #[arg(long)]
+    pub vfov: Option<f32>,
+
+    /// Lens aperture, 0 for a pinhole camera
+    #[arg(long)]
+    pub aperture: Option<f32>,
+
+    /// Distance to the plane in focus
+    #[arg(long)]
+    pub focus_dist: Option<f32>,
+
+    /// Number of render threads [default: all cores]
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+
+    /// Seed for the random number generator, fix it to reproduce a render
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Where to write the rendered image
+    #[arg(short, long, default_value = "output.png")]
+    pub output: PathBuf,
+
+    /// Also save the scene, as it is about to be rendered, to a scene file
+    #[arg(short, long, value_name = "SCENE_FILE")]
+    pub export: Option<PathBuf>,
+
+    /// Only find light by bouncing into it, without sampling the lights directly
+    #[arg(long)]
+    pub no_light_sampling: bool,
+
+    /// Trace a single wavelength per sample instead of RGB, which lets glass
+    /// disperse light
+    #[arg(long)]
+    pub spectral: bool,
+
+    /// Stop after loading (and exporting) the scene
+    #[arg(long)]
+    pub no_render: bool,
+
+    /// Time camera rays through the scene's flattened BVHs against the trees
+    /// they are built from, instead of rendering
+    #[arg(long)]
+    pub bench_bvh: bool,
+
+    /// Print how long the scene's BVHs took to build and what they look like
+    #[arg(long)]
+    pub bvh_stats: bool,
+
+    /// Image format (png, jpg, bmp, tga, tiff, ...) [default: from the output extension]
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: Option<ImageFormat>,
+}
+
+fn parse_floats<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    if parts.len() != N {
+        return Err(format!("expected {} comma-separated numbers", N));
+    }
+
+    let mut values = [0.0; N];
+    for (value, part) in values.iter_mut().zip(parts) {
+        *value = part
+            .parse()
+            .map_err(|_| format!("`{}` is not a number", part))?;
+    }
+
+    Ok(values)
+}
+
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let [x, y, z] = parse_floats(s)?;
+    Ok(Vec3::new(x, y, z))
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    let [r, g, b] = parse_floats(s)?;
+    Ok(Color::new(r, g, b))
+}
+
+fn parse_aspect_ratio(s: &str) -> Result<f32, String> {
+    let ratio = match s.split_once('/') {
+        Some((w, h)) => {
+            let [w, h] = parse_floats(&format!("{},{}", w, h))?;
+            w / h
+        }
+        None => s
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a number", s))?,
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err("aspect ratio must be positive".to_string())
+    }
+}
+
+fn parse_format(s: &str) -> Result<ImageFormat, String> {
+    match ImageFormat::from_extension(s) {
+        Some(format) if format.can_write() => Ok(format),
+        _ => Err(format!("`{}` is not a supported output format", s)),
+    }
+}
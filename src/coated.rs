@@ -0,0 +1,211 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    microfacet::{self, Ggx},
+    ray::Ray,
+    sampler::Sampler,
+    utilities::Frame,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::{any::Any, sync::Arc};
+
+// Any material under a smooth or rough layer of varnish, like car paint or
+// lacquered wood. Light either reflects off the coat or goes through it, twice,
+// to the base, so the base gets what the coat doesn't reflect on the way in and
+// the way out. The coat can absorb some of it as well, more the longer the path
+// through it is.
+pub struct Coated {
+    pub base: Arc<dyn Material>,
+    pub ir: f32, // Index of Refraction of the coat
+    pub roughness: f32,
+    pub thickness: f32,
+    // Fraction of each channel absorbed per unit of distance through the coat.
+    pub absorption: Color,
+    distribution: Ggx,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, ir: f32, roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Self {
+            base,
+            ir,
+            roughness,
+            thickness: 0.0,
+            absorption: Color::black(),
+            distribution: Ggx::from_roughness(roughness),
+        }
+    }
+
+    fn fresnel(&self, cos_i: f32) -> f32 {
+        microfacet::fresnel_dielectric(cos_i, self.ir)
+    }
+
+    // How likely `sample` is to reflect off the coat rather than go through to
+    // the base.
+    fn coat_probability(&self, wo: &Vec3) -> f32 {
+        self.fresnel(wo.z)
+    }
+
+    // The coat's own reflection, times the cosine, for a rough coat. A smooth
+    // one only reflects into the mirror direction.
+    fn coat_eval(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if self.distribution.is_smooth() || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        let m = (*wo + *wi).normalized();
+        let d = self.distribution.d(&m);
+        let g = self.distribution.g(wo, wi);
+        self.fresnel(wo.dot(m)) * d * g / (4.0 * wo.z)
+    }
+
+    fn coat_pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if self.distribution.is_smooth() || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        let m = (*wo + *wi).normalized();
+        self.distribution.visible_normal_pdf(wo, &m) / (4.0 * wo.dot(m))
+    }
+
+    // What the light the base scatters from `wi` to `wo` is multiplied by on its
+    // way through the coat and back.
+    fn base_factor(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let transmitted = (1.0 - self.fresnel(wo.z.abs())) * (1.0 - self.fresnel(wi.z.abs()));
+        if self.thickness <= 0.0 {
+            return Color::white() * transmitted;
+        }
+
+        // Both ways are bent towards the normal inside the coat.
+        let length = self.thickness
+            * (1.0 / self.refracted_cos(wo.z.abs()) + 1.0 / self.refracted_cos(wi.z.abs()));
+        let mut result = Color::black();
+        for c in 0..3 {
+            result[c] = transmitted * (-self.absorption[c] * length).exp();
+        }
+
+        result
+    }
+
+    // Cosine of the angle to the normal once a direction with cosine `cos` has
+    // been refracted into the coat.
+    fn refracted_cos(&self, cos: f32) -> f32 {
+        let sin2 = (1.0 - cos * cos).max(0.0) / (self.ir * self.ir);
+        (1.0 - sin2).max(1e-6).sqrt()
+    }
+}
+
+impl Material for Coated {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 {
+            return Color::black();
+        }
+
+        Color::white() * self.coat_eval(&wo, &wi)
+            + self.base_factor(&wo, &wi) * self.base.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+
+        let p = self.coat_probability(&wo);
+        p * self.coat_pdf(&wo, &wi) + (1.0 - p) * self.base.pdf(r_in, rec, direction)
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let p = self.coat_probability(&wo);
+        if sampler.random_float() < p {
+            if self.distribution.is_smooth() {
+                // Reflecting is picked with the probability it happens with.
+                // Lights are still sampled for the base, unless it's specular
+                // too.
+                return Some(BsdfSample {
+                    direction: frame.to_world(&Vec3::new(-wo.x, -wo.y, wo.z)),
+                    weight: Color::white(),
+                    pdf: 1.0,
+                    is_specular: true,
+                });
+            }
+
+            let m = self.distribution.sample_visible_normal(
+                &wo,
+                sampler.random_float(),
+                sampler.random_float(),
+            );
+            let wi = microfacet::reflect(&wo, &m);
+            // Reflected into the surface, by facets that hide one another.
+            if wi.z <= 0.0 {
+                return None;
+            }
+
+            // The base could have picked the same direction.
+            let direction = frame.to_world(&wi);
+            let f = Color::white() * self.coat_eval(&wo, &wi)
+                + self.base_factor(&wo, &wi) * self.base.eval(r_in, rec, &direction);
+            let pdf =
+                p * self.coat_pdf(&wo, &wi) + (1.0 - p) * self.base.pdf(r_in, rec, &direction);
+            if pdf <= 0.0 {
+                return None;
+            }
+
+            return Some(BsdfSample {
+                direction,
+                weight: f / pdf,
+                pdf,
+                is_specular: false,
+            });
+        }
+
+        let sample = self.base.sample(r_in, rec, sampler)?;
+        let wi = frame.to_local(&sample.direction.normalized());
+        let factor = self.base_factor(&wo, &wi);
+        if sample.is_specular || self.distribution.is_smooth() {
+            return Some(BsdfSample {
+                weight: sample.weight * factor / (1.0 - p),
+                pdf: sample.pdf * (1.0 - p),
+                ..sample
+            });
+        }
+
+        // And the other way around.
+        let f = Color::white() * self.coat_eval(&wo, &wi) + factor * sample.weight * sample.pdf;
+        let pdf = p * self.coat_pdf(&wo, &wi) + (1.0 - p) * sample.pdf;
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            weight: f / pdf,
+            pdf,
+            ..sample
+        })
+    }
+
+    fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
+        self.base.emitted(uv, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth() && self.base.is_specular()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
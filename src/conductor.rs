@@ -0,0 +1,144 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    microfacet::{self, Ggx},
+    ray::Ray,
+    sampler::Sampler,
+    thin_film::ThinFilm,
+    utilities::Frame,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::any::Any;
+
+// Complex indices of refraction, `eta` and `k`, of some metals at the red, green
+// and blue ends of the spectrum.
+pub const PRESETS: [(&str, [f32; 3], [f32; 3]); 4] = [
+    ("gold", [0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+    ("copper", [0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+    ("aluminum", [1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+    ("silver", [0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+];
+
+// A metal, smooth or rough, whose color comes from how much light it reflects
+// at each angle.
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub roughness: f32,
+    // An oxide layer, as on anodized or heat-tinted metal.
+    pub film: Option<ThinFilm>,
+    distribution: Ggx,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Self {
+            eta,
+            k,
+            roughness,
+            film: None,
+            distribution: Ggx::from_roughness(roughness),
+        }
+    }
+
+    // One of the `PRESETS`, by name.
+    pub fn preset(name: &str, roughness: f32) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(preset, _, _)| *preset == name)
+            .map(|(_, eta, k)| Self::new(Color::from(eta), Color::from(k), roughness))
+    }
+
+    fn fresnel(&self, r_in: &Ray, rec: &HitRecord, cos_i: f32) -> Color {
+        match &self.film {
+            Some(film) => film.reflectance(rec, r_in.wavelength, cos_i, 1.0, &self.eta, &self.k),
+            None => microfacet::fresnel_conductor(cos_i, &self.eta, &self.k),
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::black();
+        }
+
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::black();
+        }
+
+        let m = (wo + wi).normalized();
+        let d = self.distribution.d(&m);
+        let g = self.distribution.g(&wo, &wi);
+        self.fresnel(r_in, rec, wo.dot(m)) * (d * g / (4.0 * wo.z))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        let m = (wo + wi).normalized();
+        self.distribution.visible_normal_pdf(&wo, &m) / (4.0 * wo.dot(m))
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        if self.distribution.is_smooth() {
+            return Some(BsdfSample {
+                direction: frame.to_world(&Vec3::new(-wo.x, -wo.y, wo.z)),
+                weight: self.fresnel(r_in, rec, wo.z),
+                pdf: 1.0,
+                is_specular: true,
+            });
+        }
+
+        let m = self.distribution.sample_visible_normal(
+            &wo,
+            sampler.random_float(),
+            sampler.random_float(),
+        );
+        let wi = microfacet::reflect(&wo, &m);
+        // Reflected into the surface, by facets that hide one another.
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        let g = self.distribution.g(&wo, &wi) / self.distribution.g1(&wo);
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: self.fresnel(r_in, rec, wo.dot(m)) * g,
+            pdf: self.distribution.visible_normal_pdf(&wo, &m) / (4.0 * wo.dot(m)),
+            is_specular: false,
+        })
+    }
+
+    fn emitted(&self, _uv: &Vec2, _p: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
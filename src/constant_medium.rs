@@ -7,48 +7,51 @@ use crate::{
     isotropic::Isotropic,
     material::Material,
     ray::Ray,
+    sampler::Sampler,
     texture::Texture,
-    utilities,
 };
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct ConstantMedium {
-    pub boundary: Rc<Hittable>,
-    pub phase_function: Rc<dyn Material>,
+    pub boundary: Arc<Hittable>,
+    pub phase_function: Arc<dyn Material>,
     pub neg_inv_density: f32,
 }
 
 impl ConstantMedium {
-    pub fn new(b: Rc<Hittable>, d: f32, a: Rc<Texture>) -> Self {
+    pub fn new(b: Arc<Hittable>, d: f32, a: Arc<Texture>) -> Self {
         Self {
             boundary: b,
-            phase_function: Rc::new(Isotropic::new(a)),
+            phase_function: Arc::new(Isotropic::new(a)),
             neg_inv_density: -1.0 / d,
         }
     }
 
-    pub fn with_color(b: Rc<Hittable>, d: f32, c: Color) -> Self {
+    pub fn with_color(b: Arc<Hittable>, d: f32, c: Color) -> Self {
         Self {
             boundary: b,
-            phase_function: Rc::new(Isotropic::with_color(c)),
+            phase_function: Arc::new(Isotropic::with_color(c)),
             neg_inv_density: -1.0 / d,
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         // Print occasional samples when debugging. To enable, set enableDebug true.
         let enable_debug = false;
-        let debugging = enable_debug && utilities::random_float() < 0.00001;
+        let debugging = enable_debug && sampler.random_float() < 0.00001;
 
         let mut rec1;
-        if let Some(r) = self.boundary.hit(r, -f32::INFINITY, f32::INFINITY) {
+        if let Some(r) = self.boundary.hit(r, -f32::INFINITY, f32::INFINITY, sampler) {
             rec1 = r;
         } else {
             return None;
         }
 
         let mut rec2;
-        if let Some(r) = self.boundary.hit(r, rec1.t + 0.0001, f32::INFINITY) {
+        if let Some(r) = self
+            .boundary
+            .hit(r, rec1.t + 0.0001, f32::INFINITY, sampler)
+        {
             rec2 = r;
         } else {
             return None;
@@ -75,7 +78,7 @@ impl ConstantMedium {
 
         let ray_length = r.direction.mag();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * utilities::random_float().ln();
+        let hit_distance = self.neg_inv_density * sampler.random_float().ln();
 
         if hit_distance > distance_inside_boundary {
             return None;
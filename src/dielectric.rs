@@ -1,13 +1,66 @@
-use crate::{color::Color, hittable::HitRecord, material::Material, ray::Ray, utilities};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    ray::Ray,
+    sampler::Sampler,
+    thin_film::ThinFilm,
+    utilities,
+};
 use cliffy::Vector;
+use std::any::Any;
+
+// How the index of refraction of a glass changes with the wavelength, which
+// spreads white light out into its colors. Wavelengths are in nanometers, but
+// the coefficients, as they're usually published, are for micrometers.
+pub enum Dispersion {
+    // n = a + b / λ²
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ bᵢλ² / (λ² - cᵢ)
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    pub fn ir(&self, lambda: f32) -> f32 {
+        let l2 = (lambda / 1000.0) * (lambda / 1000.0);
+        match self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.max(1.0).sqrt()
+            }
+        }
+    }
+}
 
 pub struct Dielectric {
     pub ir: f32, // Index of Refraction
+    // Only taken into account when rendering spectrally, `ir` stands in for it
+    // otherwise.
+    pub dispersion: Option<Dispersion>,
+    // Coats both sides, like the soap of a bubble with an `ir` of 1.
+    pub film: Option<ThinFilm>,
 }
 
 impl Dielectric {
     pub fn new(ir: f32) -> Self {
-        Self { ir }
+        Self {
+            ir,
+            dispersion: None,
+            film: None,
+        }
+    }
+
+    // At the sodium D line, in the middle of the visible range, for `ir`.
+    pub fn with_dispersion(dispersion: Dispersion) -> Self {
+        Self {
+            ir: dispersion.ir(589.3),
+            dispersion: Some(dispersion),
+            film: None,
+        }
     }
 
     fn reflectance(cosine: f32, ref_idx: f32) -> f32 {
@@ -16,38 +69,105 @@ impl Dielectric {
         r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
     }
-}
 
-impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
-        let attenuation = Color::white();
-        let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
+    // Index of refraction for light of `wavelength`, or `ir` without one.
+    fn ir_at(&self, wavelength: Option<f32>) -> f32 {
+        match (&self.dispersion, wavelength) {
+            (Some(dispersion), Some(lambda)) => dispersion.ir(lambda),
+            _ => self.ir,
+        }
+    }
+
+    // How much is reflected depends on the wavelength with a film on top, so
+    // reflecting is picked with the probability it happens with on average and
+    // the color makes up the difference.
+    fn sample_film(
+        &self,
+        film: &ThinFilm,
+        r_in: &Ray,
+        rec: &HitRecord,
+        ir: f32,
+        sampler: &mut Sampler,
+    ) -> BsdfSample {
+        let (outside, inside) = if rec.front_face { (1.0, ir) } else { (ir, 1.0) };
+
+        let unit_direction = r_in.direction.normalized();
+        let cos_theta = rec.normal.dot(-unit_direction).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let cannot_refract = outside / inside * sin_theta > 1.0;
+
+        let reflectance = film.reflectance(
+            rec,
+            r_in.wavelength,
+            cos_theta,
+            outside,
+            &Color::new(inside, inside, inside),
+            &Color::black(),
+        );
+        let p = (reflectance.r + reflectance.g + reflectance.b) / 3.0;
+
+        let (direction, weight) = if cannot_refract {
+            (unit_direction.reflected_normal(rec.normal), Color::white())
+        } else if sampler.random_float() < p {
+            (unit_direction.reflected_normal(rec.normal), reflectance / p)
         } else {
-            self.ir
+            (
+                utilities::refract(&unit_direction, &rec.normal, outside / inside),
+                (Color::white() - reflectance) / (1.0 - p),
+            )
         };
 
+        BsdfSample {
+            direction,
+            weight,
+            pdf: 1.0,
+            is_specular: true,
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let ir = self.ir_at(r_in.wavelength);
+        if let Some(film) = &self.film {
+            return Some(self.sample_film(film, r_in, rec, ir, sampler));
+        }
+
+        let refraction_ratio = if rec.front_face { 1.0 / ir } else { ir };
+
         let unit_direction = r_in.direction.normalized();
         let cos_theta = rec.normal.dot(-unit_direction).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction;
 
-        if cannot_refract
-            || Self::reflectance(cos_theta, refraction_ratio) > utilities::random_float()
+        let direction;
+        if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > sampler.random_float()
         {
             direction = unit_direction.reflected_normal(rec.normal);
         } else {
             direction = utilities::refract(&unit_direction, &rec.normal, refraction_ratio);
         }
 
-        let scattered = Ray::with_time(rec.point, direction, r_in.time);
-
-        (true, attenuation, scattered)
+        // Reflecting or refracting is picked with the probability it happens
+        // with, so nothing is lost either way.
+        Some(BsdfSample {
+            direction,
+            weight: Color::white(),
+            pdf: 1.0,
+            is_specular: true,
+        })
     }
 
     fn emitted(&self, uv: &cliffy::Vec2, p: &cliffy::Vec3) -> Color {
         Color::black()
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
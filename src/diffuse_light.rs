@@ -1,33 +1,40 @@
-use crate::{color::Color, material::Material, texture::Texture};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+};
 use cliffy::{Vec2, Vec3};
-use std::rc::Rc;
+use std::{any::Any, sync::Arc};
 
 pub struct DiffuseLight {
-    emit: Rc<Texture>,
+    pub emit: Arc<Texture>,
 }
 
 impl DiffuseLight {
-    pub fn new(emit: Rc<Texture>) -> Self {
+    pub fn new(emit: Arc<Texture>) -> Self {
         Self { emit }
     }
 
     pub fn with_color(c: Color) -> Self {
-        let emit = Rc::new(Texture::SolidColor(c));
+        let emit = Arc::new(Texture::SolidColor(c));
 
         Self::new(emit)
     }
 }
 
 impl Material for DiffuseLight {
-    fn scatter(
-        &self,
-        r_in: &crate::ray::Ray,
-        rec: &crate::hittable::HitRecord,
-    ) -> (bool, crate::color::Color, crate::ray::Ray) {
-        (false, Default::default(), Default::default())
+    fn sample(&self, _r_in: &Ray, _rec: &HitRecord, _sampler: &mut Sampler) -> Option<BsdfSample> {
+        None
     }
 
-    fn emitted(&self, uv: &Vec2, p: &Vec3) -> crate::color::Color {
+    fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
         self.emit.value(uv, p)
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
@@ -5,23 +5,25 @@ use crate::{
     material::Material,
     ray::Ray,
     rect::{XYRect, XZRect, YZRect},
+    sampler::Sampler,
 };
 use cliffy::Vec3;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct GeoBox {
     pub min: Vec3,
     pub max: Vec3,
+    pub material: Arc<dyn Material>,
     pub sides: HittableList,
 }
 
 impl GeoBox {
-    pub fn new(p0: &Vec3, p1: &Vec3, mat: Rc<dyn Material>) -> Self {
+    pub fn new(p0: &Vec3, p1: &Vec3, mat: Arc<dyn Material>) -> Self {
         let min = *p0;
         let max = *p1;
 
         let mut sides = HittableList::empty();
-        sides.add(Rc::new(Hittable::XYRect(XYRect::new(
+        sides.add(Arc::new(Hittable::XYRect(XYRect::new(
             mat.clone(),
             p0.x,
             p1.x,
@@ -29,7 +31,7 @@ impl GeoBox {
             p1.y,
             p1.z,
         ))));
-        sides.add(Rc::new(Hittable::XYRect(XYRect::new(
+        sides.add(Arc::new(Hittable::XYRect(XYRect::new(
             mat.clone(),
             p0.x,
             p1.x,
@@ -38,7 +40,7 @@ impl GeoBox {
             p0.z,
         ))));
 
-        sides.add(Rc::new(Hittable::XZRect(XZRect::new(
+        sides.add(Arc::new(Hittable::XZRect(XZRect::new(
             mat.clone(),
             p0.x,
             p1.x,
@@ -46,7 +48,7 @@ impl GeoBox {
             p1.z,
             p1.y,
         ))));
-        sides.add(Rc::new(Hittable::XZRect(XZRect::new(
+        sides.add(Arc::new(Hittable::XZRect(XZRect::new(
             mat.clone(),
             p0.x,
             p1.x,
@@ -55,7 +57,7 @@ impl GeoBox {
             p0.y,
         ))));
 
-        sides.add(Rc::new(Hittable::YZRect(YZRect::new(
+        sides.add(Arc::new(Hittable::YZRect(YZRect::new(
             mat.clone(),
             p0.y,
             p1.y,
@@ -63,7 +65,7 @@ impl GeoBox {
             p1.z,
             p1.x,
         ))));
-        sides.add(Rc::new(Hittable::YZRect(YZRect::new(
+        sides.add(Arc::new(Hittable::YZRect(YZRect::new(
             mat.clone(),
             p0.y,
             p1.y,
@@ -72,11 +74,16 @@ impl GeoBox {
             p0.x,
         ))));
 
-        Self { min, max, sides }
+        Self {
+            min,
+            max,
+            material: mat,
+            sides,
+        }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        self.sides.hit(r, t_min, t_max)
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        self.sides.hit(r, t_min, t_max, sampler)
     }
 
     pub fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
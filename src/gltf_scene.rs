@@ -0,0 +1,548 @@
+use crate::{
+    aabb::AABB,
+    bump::{BumpMap, Bumped},
+    color::Color,
+    diffuse_light::DiffuseLight,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    instance::Instance,
+    lambertian::Lambertian,
+    linear_bvh::LinearBVH,
+    mat4::Mat4,
+    material::Material,
+    principled::Principled,
+    scenes::Scene,
+    sphere::Sphere,
+    texture::Texture,
+    triangle::{MeshVertices, TriangleMesh},
+};
+use cliffy::{Vec2, Vec3, Vector};
+use gltf::{camera::Projection, image::Format, khr_lights_punctual::Kind, mesh::Mode};
+use image::{DynamicImage, ImageBuffer};
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Imports glTF 2.0 files (`.gltf` with its buffers and images, or `.glb`).
+//
+// Each mesh is built once, in its own space, and placed in the scene by every
+// node that uses it as an instance carrying the node's world transform. Materials
+// are mapped from the metallic-roughness model onto `Principled`, with the base
+// color, metallic, roughness, emissive and transmission factors and their
+// textures, the index of refraction and the normal map. Left out are occlusion
+// textures, vertex colors, the specular, sheen, clearcoat and volume extensions,
+// texture coordinate sets other than the first and samplers' filtering and
+// wrapping. Alpha isn't used as coverage: masks are ignored, and blended
+// materials are made transmissive instead. The renderer only has area lights,
+// so punctual lights are stood in for by small glowing spheres: point lights and
+// spot lights, as point lights without a cone, at their position, directional lights
+// far away in the direction they shine from.
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import(PathBuf, gltf::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Import(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+pub struct GltfCamera {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aspect_ratio: Option<f32>,
+}
+
+pub struct GltfScene {
+    pub objects: HittableList,
+    // The first perspective camera in the scene, if any.
+    pub camera: Option<GltfCamera>,
+    pub light_count: usize,
+    // Bounds of the meshes alone, the light stand-ins can be far away.
+    pub bounds: Option<AABB>,
+}
+
+struct Light {
+    color: Color,
+    kind: Kind,
+    world: Mat4,
+}
+
+struct Importer<'a> {
+    path: &'a Path,
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<gltf::image::Data>,
+    textures: HashMap<usize, Arc<Texture>>,
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    // Built meshes by index, `None` for meshes with nothing to render.
+    meshes: HashMap<usize, Option<Arc<Hittable>>>,
+    objects: Vec<Arc<Hittable>>,
+    camera: Option<GltfCamera>,
+    lights: Vec<Light>,
+}
+
+impl<'a> Importer<'a> {
+    fn invalid(&self, message: String) -> GltfError {
+        GltfError::Invalid(self.path.to_path_buf(), message)
+    }
+
+    fn node(&mut self, node: gltf::Node, parent: &Mat4) -> Result<(), GltfError> {
+        let local = match node.transform() {
+            gltf::scene::Transform::Matrix { matrix } => Mat4::from_cols(matrix),
+            gltf::scene::Transform::Decomposed {
+                translation,
+                rotation,
+                scale,
+            } => Mat4::from_trs(&vec3(translation), rotation, &vec3(scale)),
+        };
+        let world = *parent * local;
+
+        if let Some(mesh) = node.mesh() {
+            if let Some(object) = self.mesh(&mesh)? {
+                if world == Mat4::identity() {
+                    self.objects.push(object);
+                } else if world.inverse().is_some() {
+                    self.objects.push(Arc::new(Hittable::Instance(Instance::new(
+                        object, world, None,
+                    ))));
+                } else {
+                    return Err(self.invalid("node transform can't be inverted".to_string()));
+                }
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            if let (None, Projection::Perspective(perspective)) =
+                (&self.camera, camera.projection())
+            {
+                // Cameras look down their local -Z axis with +Y up.
+                let look_from = world.transform_point(&Vec3::zero());
+                let forward = world.transform_vector(&Vec3::new(0.0, 0.0, -1.0));
+                self.camera = Some(GltfCamera {
+                    look_from,
+                    look_at: look_from + forward.normalized(),
+                    vup: world.transform_vector(&Vec3::up()).normalized(),
+                    vfov: perspective.yfov().to_degrees(),
+                    aspect_ratio: perspective.aspect_ratio(),
+                });
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let [r, g, b] = light.color();
+            self.lights.push(Light {
+                color: Color::new(r, g, b) * light.intensity(),
+                kind: light.kind(),
+                world,
+            });
+        }
+
+        for child in node.children() {
+            self.node(child, &world)?;
+        }
+
+        Ok(())
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Result<Option<Arc<Hittable>>, GltfError> {
+        if let Some(object) = self.meshes.get(&mesh.index()) {
+            return Ok(object.clone());
+        }
+
+        let mut primitives = HittableList::empty();
+        for primitive in mesh.primitives() {
+            if let Some(primitive) = self.primitive(&primitive)? {
+                primitives.add(Arc::new(Hittable::Mesh(primitive)));
+            }
+        }
+
+        let object = match primitives.objects.len() {
+            0 => None,
+            1 => primitives.objects.pop(),
+            _ => Some(Arc::new(Hittable::Bvh(LinearBVH::new(
+                &mut primitives,
+                0.0,
+                1.0,
+            )))),
+        };
+        self.meshes.insert(mesh.index(), object.clone());
+
+        Ok(object)
+    }
+
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+    ) -> Result<Option<TriangleMesh>, GltfError> {
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+
+        let positions: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions.map(vec3).collect(),
+            None => return Err(self.invalid("mesh primitive without positions".to_string())),
+        };
+
+        let normals: Vec<Vec3> = match reader.read_normals() {
+            Some(normals) => normals.map(vec3).collect(),
+            None => Vec::new(),
+        };
+
+        // glTF puts the texture origin at the top left, `Texture::Image` at the
+        // bottom left.
+        let uvs: Vec<Vec2> = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().map(|[u, v]| Vec2::new(u, 1.0 - v)).collect(),
+            None => Vec::new(),
+        };
+
+        let vertex_indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if vertex_indices.iter().any(|&i| i >= positions.len()) {
+            return Err(self.invalid("mesh index out of range".to_string()));
+        }
+        if (!normals.is_empty() && normals.len() != positions.len())
+            || (!uvs.is_empty() && uvs.len() != positions.len())
+        {
+            return Err(self.invalid("mesh attributes differ in length".to_string()));
+        }
+
+        let indices: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => vertex_indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            Mode::TriangleStrip => (2..vertex_indices.len())
+                .map(|i| {
+                    // Every other triangle of a strip is wound the other way.
+                    if i % 2 == 0 {
+                        [
+                            vertex_indices[i - 2],
+                            vertex_indices[i - 1],
+                            vertex_indices[i],
+                        ]
+                    } else {
+                        [
+                            vertex_indices[i - 1],
+                            vertex_indices[i - 2],
+                            vertex_indices[i],
+                        ]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => (2..vertex_indices.len())
+                .map(|i| [vertex_indices[0], vertex_indices[i - 1], vertex_indices[i]])
+                .collect(),
+            // Points and lines have no surface to render.
+            _ => return Ok(None),
+        };
+        if indices.is_empty() {
+            return Ok(None);
+        }
+
+        let material = self.material(&primitive.material())?;
+        let vertices = Arc::new(MeshVertices::new(positions, normals, uvs));
+        Ok(Some(TriangleMesh::new(vertices, indices, material)))
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Result<Arc<dyn Material>, GltfError> {
+        if let Some(m) = self.materials.get(&material.index()) {
+            return Ok(m.clone());
+        }
+
+        // Primitives without a material.
+        if material.index().is_none() {
+            let result: Arc<dyn Material> =
+                Arc::new(Lambertian::with_color(Color::new(0.8, 0.8, 0.8)));
+            self.materials.insert(None, result.clone());
+            return Ok(result);
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let [er, eg, eb] = material.emissive_factor();
+        let emissive = Color::new(er, eg, eb) * material.emissive_strength().unwrap_or(1.0);
+        let (mut transmission, transmission_texture) = match material.transmission() {
+            Some(t) => (t.transmission_factor(), t.transmission_texture()),
+            None => (0.0, None),
+        };
+        // Blending is taken as seeing through the surface, like glass.
+        if material.alpha_mode() == gltf::material::AlphaMode::Blend {
+            transmission = transmission.max(1.0 - alpha);
+        }
+
+        // Each factor multiplies its texture, if there is one. The principled
+        // material reads single numbers as the average of the channels, so
+        // factors that only keep one channel pick that channel out of the
+        // texture: blue for metallic, green for roughness and red for
+        // transmission.
+        let mut scaled = |factor: Color, info: Option<gltf::texture::Info>| match info {
+            Some(info) => Ok(Arc::new(Texture::Scaled(
+                factor,
+                self.texture(&info.texture())?,
+            ))),
+            None => Ok(Arc::new(Texture::SolidColor(factor))),
+        };
+        let single = |v: f32| Color::new(v, v, v);
+
+        let mut principled =
+            Principled::new(scaled(Color::new(r, g, b), pbr.base_color_texture())?);
+        principled.metallic = scaled(
+            Color::new(0.0, 0.0, 3.0 * pbr.metallic_factor()),
+            pbr.metallic_roughness_texture(),
+        )?;
+        principled.roughness = scaled(
+            Color::new(0.0, 3.0 * pbr.roughness_factor(), 0.0),
+            pbr.metallic_roughness_texture(),
+        )?;
+        principled.transmission = match transmission_texture {
+            Some(info) => scaled(Color::new(3.0 * transmission, 0.0, 0.0), Some(info))?,
+            None => Arc::new(Texture::SolidColor(single(transmission))),
+        };
+        principled.emission = scaled(emissive, material.emissive_texture())?;
+        principled.ir = material.ior().unwrap_or(1.5);
+        let result: Arc<dyn Material> = Arc::new(principled);
+
+        let result: Arc<dyn Material> = match material.normal_texture() {
+            Some(info) => Arc::new(Bumped::new(
+                result,
+                BumpMap::Normal(self.texture(&info.texture())?, info.scale()),
+            )),
+            None => result,
+        };
+
+        self.materials.insert(material.index(), result.clone());
+        Ok(result)
+    }
+
+    fn texture(&mut self, texture: &gltf::Texture) -> Result<Arc<Texture>, GltfError> {
+        let source = texture.source();
+        if let Some(t) = self.textures.get(&source.index()) {
+            return Ok(t.clone());
+        }
+
+        let data = &self.images[source.index()];
+        let image = to_dynamic_image(data)
+            .ok_or_else(|| self.invalid(format!("image {} has a bad size", source.index())))?;
+
+        // Images that live in their own files keep their path, so saving the
+        // scene refers to them instead of writing copies.
+        let path = match source.source() {
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => Some(
+                self.path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(uri),
+            ),
+            _ => None,
+        };
+
+        let texture = Arc::new(Texture::Image(image, path));
+        self.textures.insert(source.index(), texture.clone());
+        Ok(texture)
+    }
+
+    // Stand-ins for the punctual lights, sized relative to the rest of the scene.
+    fn light_objects(&self, objects: &mut HittableList, bounds: &Option<AABB>) {
+        let (center, extent) = match bounds {
+            Some(bbox) => ((bbox.min + bbox.max) / 2.0, (bbox.max - bbox.min).mag()),
+            None => (Vec3::zero(), 1.0),
+        };
+
+        for light in &self.lights {
+            let sphere = match light.kind {
+                Kind::Point | Kind::Spot { .. } => {
+                    // A sphere of radius r and radiance L has an intensity of
+                    // L * pi * r^2 in every direction.
+                    let radius = 0.01 * extent;
+                    let radiance = light.color / (PI * radius * radius);
+                    Sphere::new(
+                        light.world.transform_point(&Vec3::zero()),
+                        radius,
+                        Arc::new(DiffuseLight::with_color(radiance)),
+                    )
+                }
+                Kind::Directional => {
+                    // A distant disc of solid angle omega and radiance L gives an
+                    // illuminance of about L * omega, here with an angular
+                    // radius of two degrees.
+                    let angle = 2.0f32.to_radians();
+                    let distance = 100.0 * extent;
+                    let to_light = light
+                        .world
+                        .transform_vector(&Vec3::new(0.0, 0.0, 1.0))
+                        .normalized();
+                    let radiance = light.color / (PI * angle * angle);
+                    Sphere::new(
+                        center + distance * to_light,
+                        distance * angle.tan(),
+                        Arc::new(DiffuseLight::with_color(radiance)),
+                    )
+                }
+            };
+            objects.add(Arc::new(Hittable::Sphere(sphere)));
+        }
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+// Converts the decoded image into the `image` version the renderer uses.
+fn to_dynamic_image(data: &gltf::image::Data) -> Option<DynamicImage> {
+    let (w, h) = (data.width, data.height);
+    let pixels = data.pixels.clone();
+    let words = || -> Vec<u16> {
+        data.pixels
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect()
+    };
+    let floats = |channels: usize| -> Vec<u8> {
+        data.pixels
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .enumerate()
+            .filter(|(i, _)| i % channels < 3)
+            .map(|(_, f)| (f.clamp(0.0, 1.0) * 255.0) as u8)
+            .collect()
+    };
+
+    let image = match data.format {
+        Format::R8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, pixels)?),
+        Format::R8G8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, pixels)?),
+        Format::R8G8B8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, pixels)?),
+        Format::R8G8B8A8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, pixels)?),
+        Format::R16 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, words())?),
+        Format::R16G16 => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, words())?),
+        Format::R16G16B16 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, words())?),
+        Format::R16G16B16A16 => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, words())?),
+        Format::R32G32B32FLOAT => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, floats(3))?),
+        Format::R32G32B32A32FLOAT => {
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, floats(4))?)
+        }
+    };
+
+    Some(image)
+}
+
+// Loads the default scene of the file (or its first one).
+pub fn load(path: &Path) -> Result<GltfScene, GltfError> {
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| GltfError::Import(path.to_path_buf(), e))?;
+
+    let mut importer = Importer {
+        path,
+        buffers,
+        images,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+        objects: Vec::new(),
+        camera: None,
+        lights: Vec::new(),
+    };
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| importer.invalid("the file has no scenes".to_string()))?;
+    for node in scene.nodes() {
+        importer.node(node, &Mat4::identity())?;
+    }
+
+    let mut objects = HittableList::empty();
+    for object in importer.objects.drain(..) {
+        objects.add(object);
+    }
+    let bounds = objects.bounding_box(0.0, 1.0);
+    importer.light_objects(&mut objects, &bounds);
+
+    Ok(GltfScene {
+        objects,
+        camera: importer.camera,
+        light_count: importer.lights.len(),
+        bounds,
+    })
+}
+
+// Loads a glTF file as a whole scene to render. Without a camera in the file
+// the view is framed around the scene's bounding box, and without lights the
+// background is a bright sky so the scene is visible at all.
+pub fn load_scene(path: &Path) -> Result<Scene, GltfError> {
+    let gltf_scene = load(path)?;
+    let mut objects = gltf_scene.objects;
+
+    let bbox = gltf_scene.bounds.ok_or_else(|| {
+        GltfError::Invalid(path.to_path_buf(), "the scene has no meshes".to_string())
+    })?;
+    let center = (bbox.min + bbox.max) / 2.0;
+    let radius = (bbox.max - bbox.min).mag() / 2.0;
+
+    let camera = gltf_scene.camera.unwrap_or_else(|| GltfCamera {
+        look_from: center + Vec3::new(0.0, 0.0, 2.5 * radius),
+        look_at: center,
+        vup: Vec3::up(),
+        vfov: 40.0,
+        aspect_ratio: None,
+    });
+    let focus_dist = (camera.look_from - center).mag();
+
+    let background = if gltf_scene.light_count == 0 && !has_emitters(&objects) {
+        Color::new(0.7, 0.8, 1.0)
+    } else {
+        Color::black()
+    };
+
+    let world = if objects.objects.len() > 1 {
+        HittableList::new(Arc::new(Hittable::Bvh(LinearBVH::new(
+            &mut objects,
+            0.0,
+            1.0,
+        ))))
+    } else {
+        objects
+    };
+
+    Ok(Scene {
+        world,
+        background,
+        look_from: camera.look_from,
+        look_at: camera.look_at,
+        vup: camera.vup,
+        vfov: camera.vfov,
+        aperture: 0.0,
+        focus_dist,
+        aspect_ratio: camera.aspect_ratio.unwrap_or(16.0 / 9.0),
+        image_width: 400,
+        samples_per_pixel: 100,
+    })
+}
+
+fn has_emitters(objects: &HittableList) -> bool {
+    fn is_emitter(object: &Arc<Hittable>) -> bool {
+        match &**object {
+            Hittable::Mesh(mesh) => mesh.material.as_any().is::<DiffuseLight>(),
+            Hittable::Bvh(bvh) => bvh.objects.iter().any(is_emitter),
+            Hittable::Instance(inst) => is_emitter(&inst.object),
+            _ => false,
+        }
+    }
+
+    objects.objects.iter().any(is_emitter)
+}
@@ -1,48 +1,83 @@
 use crate::{
     aabb::AABB,
+    animated_transform::AnimatedTransform,
     bvh_node::BVHNode,
+    color::Color,
     constant_medium::ConstantMedium,
     geo_box::GeoBox,
+    hittable_list::HittableList,
+    instance::Instance,
+    linear_bvh::LinearBVH,
     material::Material,
     moving_sphere::MovingSphere,
+    perlin::Perlin,
     rect::{XYRect, XZRect, YZRect},
     rotate::RotateY,
+    sampler::Sampler,
     sphere::Sphere,
+    texture::Texture,
+    transform::Transform,
     translate::Translate,
+    triangle::{Triangle, TriangleMesh},
     Ray,
 };
 use cliffy::{Vec2, Vec3, Vector};
-use std::rc::Rc;
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct HitRecord {
     pub point: Vec3,
+    // The normal materials shade with, which smooth meshes and bump maps tilt
+    // away from `geometric_normal`, the true one of the surface. Both point
+    // against the ray.
     pub normal: Vec3,
-    pub mat: Rc<dyn Material>,
+    pub geometric_normal: Vec3,
+    // How the point moves as `uv` changes, zero for shapes without texture
+    // coordinates.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub mat: Arc<dyn Material>,
     pub t: f32,
     pub uv: Vec2,
     pub front_face: bool,
+    // Interpolated from the vertices, for meshes that come with colors.
+    pub vertex_color: Option<Color>,
 }
 
 impl HitRecord {
-    pub fn new(point: Vec3, normal: Vec3, mat: Rc<dyn Material>, t: f32, front_face: bool) -> Self {
+    pub fn new(
+        point: Vec3,
+        normal: Vec3,
+        mat: Arc<dyn Material>,
+        t: f32,
+        front_face: bool,
+    ) -> Self {
         Self {
             point,
             normal,
+            geometric_normal: normal,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
             mat,
             t,
             uv: Vec2::zero(),
             front_face,
+            vertex_color: None,
         }
     }
 
-    pub fn with_mat_only(mat: Rc<dyn Material>) -> Self {
+    pub fn with_mat_only(mat: Arc<dyn Material>) -> Self {
         Self {
             point: Vec3::zero(),
             normal: Vec3::up(),
+            geometric_normal: Vec3::up(),
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
             mat,
             t: 0.0,
             uv: Vec2::zero(),
             front_face: false,
+            vertex_color: None,
         }
     }
     #[inline]
@@ -53,6 +88,7 @@ impl HitRecord {
         } else {
             -*outward_normal
         };
+        self.geometric_normal = self.normal;
     }
 }
 
@@ -60,6 +96,7 @@ pub enum Hittable {
     Sphere(Sphere),
     MovingSphere(MovingSphere),
     Node(BVHNode),
+    Bvh(LinearBVH),
     XYRect(XYRect),
     XZRect(XZRect),
     YZRect(YZRect),
@@ -67,21 +104,35 @@ pub enum Hittable {
     Translate(Translate),
     RotateY(RotateY),
     ConstantMedium(ConstantMedium),
+    Triangle(Triangle),
+    Mesh(TriangleMesh),
+    // A handful of objects tested one after the other, for BVH leaves.
+    List(HittableList),
+    Instance(Instance),
+    Transform(Transform),
+    AnimatedTransform(AnimatedTransform),
 }
 
 impl Hittable {
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         match &self {
-            Self::Sphere(s) => s.hit(r, t_min, t_max),
-            Hittable::MovingSphere(ms) => ms.hit(r, t_min, t_max),
-            Hittable::Node(n) => n.hit(r, t_min, t_max),
-            Hittable::XYRect(rect) => rect.hit(r, t_min, t_max),
-            Hittable::XZRect(rect) => rect.hit(r, t_min, t_max),
-            Hittable::YZRect(rect) => rect.hit(r, t_min, t_max),
-            Hittable::Box(geo_box) => geo_box.hit(r, t_min, t_max),
-            Hittable::Translate(trans) => trans.hit(r, t_min, t_max),
-            Hittable::RotateY(rot) => rot.hit(r, t_min, t_max),
-            Hittable::ConstantMedium(med) => med.hit(r, t_min, t_max),
+            Self::Sphere(s) => s.hit(r, t_min, t_max, sampler),
+            Hittable::MovingSphere(ms) => ms.hit(r, t_min, t_max, sampler),
+            Hittable::Node(n) => n.hit(r, t_min, t_max, sampler),
+            Hittable::Bvh(bvh) => bvh.hit(r, t_min, t_max, sampler),
+            Hittable::XYRect(rect) => rect.hit(r, t_min, t_max, sampler),
+            Hittable::XZRect(rect) => rect.hit(r, t_min, t_max, sampler),
+            Hittable::YZRect(rect) => rect.hit(r, t_min, t_max, sampler),
+            Hittable::Box(geo_box) => geo_box.hit(r, t_min, t_max, sampler),
+            Hittable::Translate(trans) => trans.hit(r, t_min, t_max, sampler),
+            Hittable::RotateY(rot) => rot.hit(r, t_min, t_max, sampler),
+            Hittable::ConstantMedium(med) => med.hit(r, t_min, t_max, sampler),
+            Hittable::Triangle(tri) => tri.hit(r, t_min, t_max, sampler),
+            Hittable::Mesh(mesh) => mesh.hit(r, t_min, t_max, sampler),
+            Hittable::List(list) => list.hit(r, t_min, t_max, sampler),
+            Hittable::Instance(inst) => inst.hit(r, t_min, t_max, sampler),
+            Hittable::Transform(t) => t.hit(r, t_min, t_max, sampler),
+            Hittable::AnimatedTransform(t) => t.hit(r, t_min, t_max, sampler),
         }
     }
 
@@ -90,6 +141,7 @@ impl Hittable {
             Self::Sphere(s) => s.bounding_box(time0, time1),
             Hittable::MovingSphere(ms) => ms.bounding_box(time0, time1),
             Hittable::Node(n) => n.bounding_box(time0, time1),
+            Hittable::Bvh(bvh) => bvh.bounding_box(time0, time1),
             Hittable::XYRect(rect) => rect.bounding_box(time0, time1),
             Hittable::XZRect(rect) => rect.bounding_box(time0, time1),
             Hittable::YZRect(rect) => rect.bounding_box(time0, time1),
@@ -97,6 +149,54 @@ impl Hittable {
             Hittable::Translate(trans) => trans.bounding_box(time0, time1),
             Hittable::RotateY(rot) => rot.bounding_box(time0, time1),
             Hittable::ConstantMedium(med) => med.bounding_box(time0, time1),
+            Hittable::Triangle(tri) => tri.bounding_box(time0, time1),
+            Hittable::Mesh(mesh) => mesh.bounding_box(time0, time1),
+            Hittable::List(list) => list.bounding_box(time0, time1),
+            Hittable::Instance(inst) => inst.bounding_box(time0, time1),
+            Hittable::Transform(t) => t.bounding_box(time0, time1),
+            Hittable::AnimatedTransform(t) => t.bounding_box(time0, time1),
         }
     }
+
+    // Density, over solid angle, of `random` heading from `origin` in direction
+    // `v`. Only the shapes lights can be sampled on have one, it's zero for the
+    // rest.
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        match &self {
+            Self::Sphere(s) => s.pdf_value(origin, v, sampler),
+            Hittable::XYRect(rect) => rect.pdf_value(origin, v, sampler),
+            Hittable::XZRect(rect) => rect.pdf_value(origin, v, sampler),
+            Hittable::YZRect(rect) => rect.pdf_value(origin, v, sampler),
+            Hittable::List(list) => list.pdf_value(origin, v, sampler),
+            _ => 0.0,
+        }
+    }
+
+    // A random direction from `origin` towards the object.
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        match &self {
+            Self::Sphere(s) => s.random(origin, sampler),
+            Hittable::XYRect(rect) => rect.random(origin, sampler),
+            Hittable::XZRect(rect) => rect.random(origin, sampler),
+            Hittable::YZRect(rect) => rect.random(origin, sampler),
+            Hittable::List(list) => list.random(origin, sampler),
+            _ => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+// The scene is built once and then shared between the render workers, so every
+// part of it has to stay `Send + Sync`. This stops compiling as soon as a
+// non-thread-safe handle (an `Rc`, a `Cell`, ...) sneaks back into the graph.
+#[allow(dead_code)]
+fn assert_scene_is_thread_safe() {
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+    assert_send_sync::<Hittable>();
+    assert_send_sync::<HittableList>();
+    assert_send_sync::<HitRecord>();
+    assert_send_sync::<dyn Material>();
+    assert_send_sync::<Texture>();
+    assert_send_sync::<Perlin>();
+    assert_send_sync::<AABB>();
 }
@@ -1,12 +1,14 @@
-use crate::{aabb::AABB, hittable::*, ray::Ray};
-use std::rc::Rc;
+use crate::{aabb::AABB, hittable::*, ray::Ray, sampler::Sampler};
+use cliffy::Vec3;
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct HittableList {
-    pub objects: Vec<Rc<Hittable>>,
+    pub objects: Vec<Arc<Hittable>>,
 }
 
 impl HittableList {
-    pub fn new(object: Rc<Hittable>) -> Self {
+    pub fn new(object: Arc<Hittable>) -> Self {
         Self {
             objects: vec![object],
         }
@@ -22,16 +24,16 @@ impl HittableList {
         self.objects.clear();
     }
 
-    pub fn add(&mut self, object: Rc<Hittable>) {
+    pub fn add(&mut self, object: Arc<Hittable>) {
         self.objects.push(object);
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         let mut temp_rec = None;
         let mut closest_so_far = t_max;
 
         for object in &self.objects {
-            if let Some(hit_record) = object.hit(r, t_min, closest_so_far) {
+            if let Some(hit_record) = object.hit(r, t_min, closest_so_far, sampler) {
                 closest_so_far = hit_record.t;
                 temp_rec = Some(hit_record);
             }
@@ -62,4 +64,24 @@ impl HittableList {
 
         Some(output_box)
     }
+
+    // Picks one of the objects at random and heads for it, so the density is
+    // the average of theirs.
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f32 = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, v, sampler))
+            .sum();
+        sum / self.objects.len() as f32
+    }
+
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let index = sampler.random_int(0, self.objects.len() as i32 - 1);
+        self.objects[index as usize].random(origin, sampler)
+    }
 }
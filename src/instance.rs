@@ -0,0 +1,63 @@
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    mat4::Mat4,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    transform,
+};
+use std::sync::Arc;
+
+// Places a shared object, typically a mesh or a BVH of its own, in the scene
+// under a transform and optionally with another material. Any number of
+// instances can point at the same object, which is only stored and built once.
+pub struct Instance {
+    pub object: Arc<Hittable>,
+    pub transform: Mat4,
+    pub inverse: Mat4,
+    pub material: Option<Arc<dyn Material>>,
+    pub bbox: Option<AABB>,
+}
+
+impl Instance {
+    pub fn new(
+        object: Arc<Hittable>,
+        transform: Mat4,
+        material: Option<Arc<dyn Material>>,
+    ) -> Self {
+        let inverse = transform
+            .inverse()
+            .expect("an instance transform has to be invertible");
+        let bbox = object
+            .bounding_box(0.0, 1.0)
+            .map(|bbox| bbox.transformed(&transform));
+
+        Self {
+            object,
+            transform,
+            inverse,
+            material,
+            bbox,
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let local_r = transform::ray_to_local(&self.inverse, r);
+
+        if let Some(mut rec) = self.object.hit(&local_r, t_min, t_max, sampler) {
+            transform::hit_to_world(&self.transform, &self.inverse, &mut rec);
+            if let Some(material) = &self.material {
+                rec.mat = material.clone();
+            }
+
+            Some(rec)
+        } else {
+            None
+        }
+    }
+
+    pub fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        self.bbox.clone()
+    }
+}
@@ -1,35 +1,55 @@
 use cliffy::{Vec2, Vec3};
 
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, texture::Texture, utilities,
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
 };
-use std::rc::Rc;
+use std::{any::Any, f32::consts::PI, sync::Arc};
 
 pub struct Isotropic {
-    pub albedo: Rc<Texture>,
+    pub albedo: Arc<Texture>,
 }
 
 impl Isotropic {
-    pub fn new(albedo: Rc<Texture>) -> Self {
+    pub fn new(albedo: Arc<Texture>) -> Self {
         Self { albedo }
     }
 
     pub fn with_color(c: Color) -> Self {
         Self {
-            albedo: Rc::new(Texture::SolidColor(c)),
+            albedo: Arc::new(Texture::SolidColor(c)),
         }
     }
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
-        let scattered = Ray::with_time(rec.point, utilities::random_in_sphere(), r_in.time);
-        let attenuation = self.albedo.value(&rec.uv, &rec.point);
+    // Scatters equally in all directions.
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, _direction: &Vec3) -> Color {
+        self.albedo.value_at(rec) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
 
-        (true, attenuation, scattered)
+    fn sample(&self, _r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        Some(BsdfSample {
+            direction: sampler.random_in_sphere(),
+            weight: self.albedo.value_at(rec),
+            pdf: 1.0 / (4.0 * PI),
+            is_specular: false,
+        })
     }
 
     fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
         Color::black()
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
@@ -1,40 +1,65 @@
 use crate::{
-    color::Color, hittable::HitRecord, material::Material, ray::Ray, texture::Texture, utilities,
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+    utilities,
 };
-use std::rc::Rc;
+use cliffy::{Vec3, Vector};
+use std::{any::Any, f32::consts::PI, sync::Arc};
 
 pub struct Lambertian {
-    pub albedo: Rc<Texture>,
+    pub albedo: Arc<Texture>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Rc<Texture>) -> Self {
+    pub fn new(albedo: Arc<Texture>) -> Self {
         Self { albedo }
     }
 
     pub fn with_color(c: Color) -> Self {
         Self {
-            albedo: Rc::new(Texture::SolidColor(c)),
+            albedo: Arc::new(Texture::SolidColor(c)),
         }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
-        let mut scatter_direction = rec.normal + utilities::random_unit_vec3();
+    // Reflects equally in all directions, so only the cosine is left.
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let cosine = rec.normal.dot(direction.normalized());
+        self.albedo.value_at(rec) * (cosine.max(0.0) / PI)
+    }
+
+    // The directions `sample` picks are cosine-weighted.
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        let cosine = rec.normal.dot(direction.normalized());
+        cosine.max(0.0) / PI
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let mut direction = rec.normal + sampler.random_unit_vec3();
 
         // Catch degenerate scatter direction
-        if utilities::near_zero(&scatter_direction) {
-            scatter_direction = rec.normal;
+        if utilities::near_zero(&direction) {
+            direction = rec.normal;
         }
 
-        let scattered = Ray::with_time(rec.point, scatter_direction, r_in.time);
-        let attenuation = self.albedo.value(&rec.uv, &rec.point);
-
-        (true, attenuation, scattered)
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo.value_at(rec),
+            pdf: self.pdf(r_in, rec, &direction),
+            is_specular: false,
+        })
     }
 
     fn emitted(&self, uv: &cliffy::Vec2, p: &cliffy::Vec3) -> Color {
         Color::black()
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
@@ -0,0 +1,37 @@
+use crate::{diffuse_light::DiffuseLight, hittable::Hittable, hittable_list::HittableList};
+use std::sync::Arc;
+
+// Gathers the lights that can be sampled directly: spheres and rectangles made
+// of `DiffuseLight`, at the top of the scene or in its BVHs and lists. Anything
+// else that glows, like an emissive mesh or a moved light, is still found by
+// rays bouncing into it, only not as quickly.
+pub fn find(world: &HittableList) -> HittableList {
+    let mut lights = HittableList::empty();
+    for object in &world.objects {
+        collect(object, &mut lights);
+    }
+
+    lights
+}
+
+fn collect(object: &Arc<Hittable>, lights: &mut HittableList) {
+    let material = match &**object {
+        Hittable::Sphere(s) => &s.material,
+        Hittable::XYRect(r) => &r.mp,
+        Hittable::XZRect(r) => &r.mp,
+        Hittable::YZRect(r) => &r.mp,
+        Hittable::Bvh(bvh) => {
+            bvh.objects.iter().for_each(|o| collect(o, lights));
+            return;
+        }
+        Hittable::List(list) => {
+            list.objects.iter().for_each(|o| collect(o, lights));
+            return;
+        }
+        _ => return,
+    };
+
+    if material.as_any().is::<DiffuseLight>() {
+        lights.add(object.clone());
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{
+    aabb::AABB,
+    bvh_node::{BVHNode, INTERSECTION_COST, TRAVERSAL_COST},
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    ray::Ray,
+    sampler::Sampler,
+};
+use std::{fmt, sync::Arc};
+
+// Traversal keeps the nodes still to visit on a stack of this size, unless the
+// tree is deeper than that.
+const STACK_SIZE: usize = 64;
+
+// A BVH laid out flat in depth-first order, so a node's first child is the one
+// right after it. Leaves point at a run of `objects`.
+pub struct LinearBVH {
+    pub nodes: Vec<LinearNode>,
+    pub objects: Vec<Arc<Hittable>>,
+    depth: usize,
+}
+
+pub struct LinearNode {
+    pub bbox: AABB,
+    pub kind: NodeKind,
+}
+
+pub enum NodeKind {
+    Leaf { first: usize, count: usize },
+    // The first child lies towards the negative side of `axis`.
+    Interior { second_child: usize, axis: usize },
+}
+
+impl LinearBVH {
+    pub fn new(list: &mut HittableList, time0: f32, time1: f32) -> Self {
+        Self::from_tree(&BVHNode::new(list, time0, time1), time0, time1)
+    }
+
+    pub fn from_tree(root: &BVHNode, time0: f32, time1: f32) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            objects: Vec::new(),
+            depth: 0,
+        };
+
+        if Arc::ptr_eq(&root.left, &root.right) {
+            bvh.push_leaf(&root.left, root.bbox.clone());
+        } else {
+            bvh.flatten_node(root, 1, time0, time1);
+        }
+
+        bvh
+    }
+
+    fn flatten(&mut self, object: &Arc<Hittable>, depth: usize, time0: f32, time1: f32) {
+        match &**object {
+            Hittable::Node(n) => self.flatten_node(n, depth, time0, time1),
+            _ => {
+                let bbox = object.bounding_box(time0, time1).unwrap_or_default();
+                self.push_leaf(object, bbox);
+            }
+        }
+    }
+
+    fn flatten_node(&mut self, node: &BVHNode, depth: usize, time0: f32, time1: f32) {
+        self.depth = self.depth.max(depth);
+
+        let left_box = node.left.bounding_box(time0, time1).unwrap_or_default();
+        let right_box = node.right.bounding_box(time0, time1).unwrap_or_default();
+        let left_center = 0.5 * (left_box.min + left_box.max);
+        let right_center = 0.5 * (right_box.min + right_box.max);
+
+        // Split along the axis the children are furthest apart on, which is
+        // what decides the order to visit them in.
+        let offset = right_center - left_center;
+        let axis = (0..3)
+            .max_by(|&a, &b| offset[a].abs().partial_cmp(&offset[b].abs()).unwrap())
+            .unwrap_or(0);
+        let (first, second) = if offset[axis] < 0.0 {
+            (&node.right, &node.left)
+        } else {
+            (&node.left, &node.right)
+        };
+
+        let index = self.nodes.len();
+        self.nodes.push(LinearNode {
+            bbox: node.bbox.clone(),
+            kind: NodeKind::Interior {
+                second_child: 0,
+                axis,
+            },
+        });
+
+        self.flatten(first, depth + 1, time0, time1);
+        let second_child = self.nodes.len();
+        self.flatten(second, depth + 1, time0, time1);
+
+        self.nodes[index].kind = NodeKind::Interior { second_child, axis };
+    }
+
+    fn push_leaf(&mut self, object: &Arc<Hittable>, bbox: AABB) {
+        let first = self.objects.len();
+        match &**object {
+            Hittable::List(list) => self.objects.extend(list.objects.iter().cloned()),
+            _ => self.objects.push(object.clone()),
+        }
+
+        self.nodes.push(LinearNode {
+            bbox,
+            kind: NodeKind::Leaf {
+                first,
+                count: self.objects.len() - first,
+            },
+        });
+    }
+
+    // Rebuilds the pointer tree this was flattened from, passing every object
+    // through `map` on the way.
+    pub fn to_tree(&self, map: &dyn Fn(&Arc<Hittable>) -> Arc<Hittable>) -> BVHNode {
+        match self.nodes[0].kind {
+            NodeKind::Interior { .. } => self.tree_node(0, map),
+            NodeKind::Leaf { .. } => {
+                let leaf = self.tree_child(0, map);
+                BVHNode {
+                    left: leaf.clone(),
+                    right: leaf,
+                    bbox: self.nodes[0].bbox.clone(),
+                }
+            }
+        }
+    }
+
+    fn tree_node(&self, index: usize, map: &dyn Fn(&Arc<Hittable>) -> Arc<Hittable>) -> BVHNode {
+        let second_child = match self.nodes[index].kind {
+            NodeKind::Interior { second_child, .. } => second_child,
+            NodeKind::Leaf { .. } => unreachable!("only interior nodes become tree nodes"),
+        };
+
+        BVHNode {
+            left: self.tree_child(index + 1, map),
+            right: self.tree_child(second_child, map),
+            bbox: self.nodes[index].bbox.clone(),
+        }
+    }
+
+    fn tree_child(
+        &self,
+        index: usize,
+        map: &dyn Fn(&Arc<Hittable>) -> Arc<Hittable>,
+    ) -> Arc<Hittable> {
+        match self.nodes[index].kind {
+            NodeKind::Interior { .. } => Arc::new(Hittable::Node(self.tree_node(index, map))),
+            NodeKind::Leaf { first, count: 1 } => map(&self.objects[first]),
+            NodeKind::Leaf { first, count } => Arc::new(Hittable::List(HittableList {
+                objects: self.objects[first..first + count].iter().map(map).collect(),
+            })),
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        if self.depth <= STACK_SIZE {
+            self.traverse(&mut [0; STACK_SIZE], r, t_min, t_max, sampler)
+        } else {
+            self.traverse(&mut vec![0; self.depth], r, t_min, t_max, sampler)
+        }
+    }
+
+    fn traverse(
+        &self,
+        stack: &mut [usize],
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
+        let dir_is_neg = [
+            r.direction.x < 0.0,
+            r.direction.y < 0.0,
+            r.direction.z < 0.0,
+        ];
+
+        let mut closest_so_far = t_max;
+        let mut temp_rec = None;
+        let mut to_visit = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if node.bbox.hit(r, t_min, closest_so_far) {
+                match node.kind {
+                    NodeKind::Leaf { first, count } => {
+                        for object in &self.objects[first..first + count] {
+                            if let Some(rec) = object.hit(r, t_min, closest_so_far, sampler) {
+                                closest_so_far = rec.t;
+                                temp_rec = Some(rec);
+                            }
+                        }
+                    }
+                    NodeKind::Interior { second_child, axis } => {
+                        // Visit the nearer child first, so the further one can
+                        // often be skipped.
+                        if dir_is_neg[axis] {
+                            stack[to_visit] = current + 1;
+                            current = second_child;
+                        } else {
+                            stack[to_visit] = second_child;
+                            current += 1;
+                        }
+                        to_visit += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if to_visit == 0 {
+                break;
+            }
+            to_visit -= 1;
+            current = stack[to_visit];
+        }
+
+        temp_rec
+    }
+
+    pub fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        Some(self.nodes[0].bbox.clone())
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        let mut stats = BVHStats {
+            nodes: 0,
+            leaves: 0,
+            objects: self.objects.len(),
+            max_depth: self.depth,
+            max_leaf_size: 0,
+            sah_cost: 0.0,
+        };
+
+        let root_area = self.nodes[0].bbox.surface_area();
+        for node in &self.nodes {
+            let area = if root_area > 0.0 {
+                node.bbox.surface_area() / root_area
+            } else {
+                1.0
+            };
+
+            match node.kind {
+                NodeKind::Interior { .. } => {
+                    stats.nodes += 1;
+                    stats.sah_cost += TRAVERSAL_COST * area;
+                }
+                NodeKind::Leaf { count, .. } => {
+                    stats.leaves += 1;
+                    stats.max_leaf_size = stats.max_leaf_size.max(count);
+                    stats.sah_cost += INTERSECTION_COST * count as f32 * area;
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+// Shape of a built tree, to tell how well it is likely to perform.
+pub struct BVHStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub objects: usize,
+    pub max_depth: usize,
+    pub max_leaf_size: usize,
+    // Expected cost of tracing a ray that hits the root, in object intersections.
+    pub sah_cost: f32,
+}
+
+impl fmt::Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} objects, {} nodes, {} leaves (avg {:.2}, max {} objects), depth {}, SAH cost {:.2}",
+            self.objects,
+            self.nodes,
+            self.leaves,
+            self.objects as f32 / self.leaves.max(1) as f32,
+            self.max_leaf_size,
+            self.max_depth,
+            self.sah_cost
+        )
+    }
+}
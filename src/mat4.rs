@@ -0,0 +1,178 @@
+use crate::quaternion::{self, Quaternion};
+use cliffy::Vec3;
+use std::ops::Mul;
+
+// A 4x4 matrix for affine transforms, stored row by row. Points are column
+// vectors, so `a * b` applies `b` first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Self {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // From the column-major layout glTF and most file formats use.
+    pub fn from_cols(cols: [[f32; 4]; 4]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (c, col) in cols.iter().enumerate() {
+            for (r, value) in col.iter().enumerate() {
+                m[r][c] = *value;
+            }
+        }
+
+        Self { m }
+    }
+
+    pub fn translation(offset: &Vec3) -> Self {
+        let mut result = Self::identity();
+        result.m[0][3] = offset.x;
+        result.m[1][3] = offset.y;
+        result.m[2][3] = offset.z;
+        result
+    }
+
+    pub fn scaling(scale: &Vec3) -> Self {
+        let mut result = Self::identity();
+        result.m[0][0] = scale.x;
+        result.m[1][1] = scale.y;
+        result.m[2][2] = scale.z;
+        result
+    }
+
+    // Rotation by a unit quaternion given as [x, y, z, w].
+    pub fn rotation(q: Quaternion) -> Self {
+        let [x, y, z, w] = q;
+        Self {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - z * w),
+                    2.0 * (x * z + y * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y + z * w),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - x * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z - y * w),
+                    2.0 * (y * z + x * w),
+                    1.0 - 2.0 * (x * x + y * y),
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // Rotation by `degrees` counter-clockwise around `axis`, looking down it.
+    pub fn rotation_axis(axis: &Vec3, degrees: f32) -> Self {
+        Self::rotation(quaternion::from_axis_angle(axis, degrees))
+    }
+
+    // Rotation around X, then Y, then Z, by the angles in degrees.
+    pub fn rotation_euler(degrees: &Vec3) -> Self {
+        Self::rotation(quaternion::from_euler(degrees))
+    }
+
+    // Scale first, then rotate, then translate.
+    pub fn from_trs(translation: &Vec3, rotation: Quaternion, scale: &Vec3) -> Self {
+        Self::translation(translation) * Self::rotation(rotation) * Self::scaling(scale)
+    }
+
+    // Determinant of the upper 3x3 part, negative when the transform mirrors.
+    pub fn determinant3(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // Inverse of an affine transform, `None` if it squashes space flat.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant3();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let m = &self.m;
+        let inv_det = 1.0 / det;
+        let mut result = Self::identity();
+        result.m[0][0] = (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det;
+        result.m[0][1] = (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det;
+        result.m[0][2] = (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det;
+        result.m[1][0] = (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det;
+        result.m[1][1] = (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det;
+        result.m[1][2] = (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det;
+        result.m[2][0] = (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det;
+        result.m[2][1] = (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det;
+        result.m[2][2] = (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det;
+
+        // The inverse translation is the original one run through the inverse
+        // of the linear part, negated.
+        let t = Vec3::new(m[0][3], m[1][3], m[2][3]);
+        let t = result.transform_vector(&t);
+        result.m[0][3] = -t.x;
+        result.m[1][3] = -t.y;
+        result.m[2][3] = -t.z;
+
+        Some(result)
+    }
+
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // Applies the transpose. Normals go through the transpose of the inverse
+    // transform, `inverse.transform_normal(n)`, which keeps them perpendicular
+    // to the surface under non-uniform scaling. The result is not normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[r][k] * rhs.m[k][c]).sum();
+            }
+        }
+
+        Self { m }
+    }
+}
@@ -1,8 +1,51 @@
-use crate::{color::Color, hittable::HitRecord, ray::Ray};
+use crate::{color::Color, hittable::HitRecord, medium::Medium, ray::Ray, sampler::Sampler};
 use cliffy::{Vec2, Vec3};
+use std::any::Any;
 
-pub trait Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray);
+// A direction picked by `Material::sample`.
+pub struct BsdfSample {
+    pub direction: Vec3,
+    // What the light arriving from `direction` is multiplied by: `eval` over
+    // `pdf`, or for specular samples the fraction of light that goes that way.
+    pub weight: Color,
+    pub pdf: f32,
+    // Mirror-like reflection or refraction into a single direction, which `eval`
+    // and `pdf` know nothing about.
+    pub is_specular: bool,
+}
+
+// Directions are towards the surface for `r_in` and away from it for the one
+// scattered into, neither has to be normalized.
+pub trait Material: Send + Sync {
+    // The BSDF for light arriving from `direction` and leaving back along `r_in`,
+    // times the cosine of the angle `direction` makes with the normal. Volumes
+    // have no normal and give their phase function instead.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> Color {
+        Color::black()
+    }
+
+    // Density, over solid angle, of `sample` picking `direction`.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    // Picks a direction for the ray to go on in, or `None` if it's absorbed.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample>;
 
     fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color;
+
+    // Whether `sample` only ever picks specular directions, leaving `eval` zero
+    // everywhere and nothing for sampling lights to find.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    // What fills the inside of a closed surface, for rays that `sample` sends
+    // through it at `rec` from the outside.
+    fn medium(&self, _rec: &HitRecord) -> Option<Medium> {
+        None
+    }
+
+    // Lets the scene exporter find out which concrete material it's looking at.
+    fn as_any(&self) -> &dyn Any;
 }
@@ -0,0 +1,115 @@
+use crate::{color::Color, sampler::Sampler, utilities::Frame};
+use cliffy::Vec3;
+use std::f32::consts::PI;
+
+// Participating medium filling the inside of a closed surface, through which
+// rays take a random walk: they travel some distance, scatter off a particle
+// into a new direction, and so on until they find their way out.
+#[derive(Clone, Copy)]
+pub struct Medium {
+    // Chance of running into a particle, per unit of distance, for each channel.
+    pub sigma_t: Color,
+    // Fraction of the light a particle scatters rather than absorbs.
+    pub albedo: Color,
+    // Henyey-Greenstein asymmetry, from -1 (back) through 0 (any direction) to 1
+    // (forward).
+    pub anisotropy: f32,
+}
+
+impl Medium {
+    // Takes the color the medium ends up with after light has scattered around
+    // in it many times, which is what one usually has in mind, and works out the
+    // albedo of a single scattering event that gives it (van de Hulst's
+    // approximation, as used by Cycles).
+    pub fn new(color: Color, mean_free_path: Color, anisotropy: f32) -> Self {
+        let mut sigma_t = Color::black();
+        let mut albedo = Color::black();
+        for c in 0..3 {
+            sigma_t[c] = 1.0 / mean_free_path[c].max(1e-6);
+
+            let a = color[c].clamp(0.0, 1.0);
+            let t = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            albedo[c] = (1.0 - t * t).clamp(0.0, 1.0);
+        }
+
+        Self {
+            sigma_t,
+            albedo,
+            anisotropy: anisotropy.clamp(-0.99, 0.99),
+        }
+    }
+
+    // Picks how far a ray goes before scattering, up to `max` where it would
+    // leave the medium, along with what the light is multiplied by. The distance
+    // is drawn for one channel, picked in proportion to how much of it the walk
+    // has left in `throughput`, and weighed by how likely any of them is to have
+    // drawn it.
+    pub fn sample_distance(
+        &self,
+        max: f32,
+        throughput: &Color,
+        sampler: &mut Sampler,
+    ) -> (f32, Color) {
+        let total = throughput.r + throughput.g + throughput.b;
+        let mut probabilities = if total > 0.0 {
+            *throughput / total
+        } else {
+            Color::new(1.0, 1.0, 1.0) / 3.0
+        };
+
+        let u = sampler.random_float();
+        let channel = if u < probabilities.r {
+            0
+        } else if u < probabilities.r + probabilities.g {
+            1
+        } else {
+            2
+        };
+        let distance = -(1.0 - sampler.random_float()).ln() / self.sigma_t[channel];
+
+        let scattered = distance < max;
+        let distance = distance.min(max);
+        let mut transmittance = Color::black();
+        for c in 0..3 {
+            transmittance[c] = (-self.sigma_t[c] * distance).exp();
+        }
+
+        let density = if scattered {
+            self.sigma_t * transmittance
+        } else {
+            transmittance
+        };
+        probabilities *= density;
+        let pdf = probabilities.r + probabilities.g + probabilities.b;
+        if pdf <= 0.0 {
+            return (distance, Color::black());
+        }
+
+        if scattered {
+            (distance, self.albedo * density / pdf)
+        } else {
+            (distance, density / pdf)
+        }
+    }
+
+    // Scatters a ray going in `direction`, which is normalized. The phase
+    // function is sampled exactly, so there's no weight to it.
+    pub fn sample_direction(&self, direction: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let g = self.anisotropy;
+        let u = sampler.random_float();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 + g - 2.0 * g * u);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random_float();
+        Frame::new(direction).to_world(&Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
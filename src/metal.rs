@@ -1,5 +1,12 @@
-use crate::{color::Color, hittable::HitRecord, material::Material, ray::Ray, utilities};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    ray::Ray,
+    sampler::Sampler,
+};
 use cliffy::Vector;
+use std::any::Any;
 
 pub struct Metal {
     pub albedo: Color,
@@ -16,23 +23,34 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+    // There's no density for the fuzzed directions, so even a fuzzy metal is
+    // treated as a mirror and lights are only found by reflecting into them.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
         let relflected = r_in.direction.normalized().reflected(rec.normal);
-        let scattered = Ray::with_time(
-            rec.point,
-            relflected + self.fuzz * utilities::random_in_sphere(),
-            r_in.time,
-        );
-        let attenuation = self.albedo;
-
-        (
-            scattered.direction.dot(rec.normal) > 0.0,
-            attenuation,
-            scattered,
-        )
+        let direction = relflected + self.fuzz * sampler.random_in_sphere();
+
+        // Fuzzed to below the surface, where it's absorbed.
+        if direction.dot(rec.normal) <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction,
+            weight: self.albedo,
+            pdf: 1.0,
+            is_specular: true,
+        })
     }
 
     fn emitted(&self, uv: &cliffy::Vec2, p: &cliffy::Vec3) -> Color {
         Color::black()
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
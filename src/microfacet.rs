@@ -0,0 +1,247 @@
+use crate::{color::Color, sampler::Sampler};
+use cliffy::{Vec3, Vector};
+use std::f32::consts::PI;
+
+// Below this `alpha` a surface is as good as smooth, and is treated as a
+// perfect mirror, or window, instead.
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+// The GGX (Trowbridge-Reitz) distribution of microfacet normals, with Smith's
+// height-correlated shadowing. Directions are in the surface's local frame,
+// where the normal is +Z, point away from the surface and are normalized.
+pub struct Ggx {
+    pub alpha: f32,
+}
+
+impl Ggx {
+    // `roughness` squared, which makes it look about linear to the eye.
+    pub fn from_roughness(roughness: f32) -> Self {
+        Self {
+            alpha: roughness * roughness,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH_ALPHA
+    }
+
+    pub fn d(&self, m: &Vec3) -> f32 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+
+        let alpha2 = self.alpha * self.alpha;
+        let t = m.z * m.z * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * t * t)
+    }
+
+    fn lambda(&self, w: &Vec3) -> f32 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f32::INFINITY;
+        }
+
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * (-1.0 + (1.0 + self.alpha * self.alpha * tan2).sqrt())
+    }
+
+    // Fraction of the microfacets facing `w` that can be seen from it.
+    pub fn g1(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Fraction that can be seen from both directions.
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Picks a microfacet normal among those that can be seen from `wo`,
+    // following "Sampling the GGX Distribution of Visible Normals" (Heitz 2018).
+    pub fn sample_visible_normal(&self, wo: &Vec3, u1: f32, u2: f32) -> Vec3 {
+        // Stretch the view so the distribution becomes that of a hemisphere.
+        let vh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalized();
+
+        let len2 = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / len2.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // And back.
+        Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).normalized()
+    }
+
+    // Density of `sample_visible_normal` picking `m`.
+    pub fn visible_normal_pdf(&self, wo: &Vec3, m: &Vec3) -> f32 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+
+        self.g1(wo) * wo.dot(*m).max(0.0) * self.d(m) / wo.z
+    }
+}
+
+// Light scattered by a rough boundary between two dielectrics, `eta` being how
+// much denser the side of `wi` is when it goes through, after "Microfacet
+// Models for Refraction through Rough Surfaces" (Walter et al. 2007). The BSDF
+// times the cosine, for light arriving from `wi` and leaving along `wo`.
+pub fn dielectric_eval(distribution: &Ggx, wo: &Vec3, wi: &Vec3, eta: f32) -> f32 {
+    let m = match dielectric_half_vector(wo, wi, eta) {
+        Some(m) => m,
+        None => return 0.0,
+    };
+
+    let d = distribution.d(&m);
+    let g = distribution.g(wo, wi);
+    let f = fresnel_dielectric(wo.dot(m), eta);
+    if wi.z > 0.0 {
+        f * d * g / (4.0 * wo.z)
+    } else {
+        (1.0 - f) * d * g * wo.dot(m) * refraction_jacobian(wo, wi, &m, eta) / wo.z
+    }
+}
+
+// Density of `dielectric_sample` picking `wi`.
+pub fn dielectric_pdf(distribution: &Ggx, wo: &Vec3, wi: &Vec3, eta: f32) -> f32 {
+    let m = match dielectric_half_vector(wo, wi, eta) {
+        Some(m) => m,
+        None => return 0.0,
+    };
+
+    let pdf_m = distribution.visible_normal_pdf(wo, &m);
+    let f = fresnel_dielectric(wo.dot(m), eta);
+    if wi.z > 0.0 {
+        f * pdf_m / (4.0 * wo.dot(m))
+    } else {
+        (1.0 - f) * pdf_m * refraction_jacobian(wo, wi, &m, eta)
+    }
+}
+
+// Reflects or refracts off a visible microfacet, each with the probability it
+// happens with, so the Fresnel term cancels out of the weight. `None` if the
+// direction ends up on the wrong side of the surface, through facets hiding one
+// another.
+pub fn dielectric_sample(
+    distribution: &Ggx,
+    wo: &Vec3,
+    eta: f32,
+    sampler: &mut Sampler,
+) -> Option<Vec3> {
+    let m = distribution.sample_visible_normal(wo, sampler.random_float(), sampler.random_float());
+    let f = fresnel_dielectric(wo.dot(m), eta);
+    let wi = match refract(wo, &m, eta) {
+        Some(wi) if sampler.random_float() >= f => wi,
+        _ => reflect(wo, &m),
+    };
+
+    if wi.z == 0.0 || (wi.z > 0.0) != (wi.dot(m) > 0.0) {
+        return None;
+    }
+
+    Some(wi)
+}
+
+// The microfacet normal that takes `wo` to `wi`, on the side of `wo`, or `None`
+// if no facet can.
+fn dielectric_half_vector(wo: &Vec3, wi: &Vec3, eta: f32) -> Option<Vec3> {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return None;
+    }
+
+    let m = if wi.z > 0.0 {
+        *wo + *wi
+    } else {
+        -(*wo + eta * *wi)
+    };
+    if m.mag_sq() == 0.0 {
+        return None;
+    }
+
+    let m = m.normalized();
+    let m = if m.z < 0.0 { -m } else { m };
+    // Light has to come and go on the sides of the facet it's meant to.
+    if wo.dot(m) <= 0.0 || (wi.dot(m) > 0.0) != (wi.z > 0.0) {
+        return None;
+    }
+
+    Some(m)
+}
+
+// Change in the density of microfacet normals over that of directions refracted
+// into.
+fn refraction_jacobian(wo: &Vec3, wi: &Vec3, m: &Vec3, eta: f32) -> f32 {
+    let denom = wo.dot(*m) + eta * wi.dot(*m);
+    eta * eta * wi.dot(*m).abs() / (denom * denom)
+}
+
+pub fn reflect(w: &Vec3, m: &Vec3) -> Vec3 {
+    2.0 * w.dot(*m) * *m - *w
+}
+
+// Bends `w` through a surface with normal `m` on its side, into a medium `eta`
+// times as dense as the one it comes from. `None` when it's reflected entirely.
+pub fn refract(w: &Vec3, m: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = w.dot(*m);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*w / eta + (cos_i / eta - cos_t) * *m)
+}
+
+// Fraction of unpolarized light reflected off a dielectric, coming in at the
+// angle with cosine `cos_i` from a medium `eta` times less dense than the other.
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+// The same off a metal in air, whose index of refraction is the complex number
+// `eta + ik`, for each channel.
+pub fn fresnel_conductor(cos_i: f32, eta: &Color, k: &Color) -> Color {
+    let mut result = Color::black();
+    for c in 0..3 {
+        result[c] = fresnel_conductor_channel(cos_i.clamp(0.0, 1.0), eta[c], k[c]);
+    }
+
+    result
+}
+
+fn fresnel_conductor_channel(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+
+    let t0 = eta * eta - k * k - sin2;
+    let a2b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+
+    let t1 = a2b2 + cos2;
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rs + rp)
+}
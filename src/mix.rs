@@ -0,0 +1,116 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    medium::Medium,
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+};
+use cliffy::{Vec2, Vec3};
+use std::{any::Any, sync::Arc};
+
+// A blend of two materials, like rust patches on metal or dirt on paint, by a
+// `factor` read from a texture: 0 is all `first`, 1 all `second`, and the average
+// of the channels in between.
+pub struct Mix {
+    pub first: Arc<dyn Material>,
+    pub second: Arc<dyn Material>,
+    pub factor: Arc<Texture>,
+}
+
+impl Mix {
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, factor: Arc<Texture>) -> Self {
+        Self {
+            first,
+            second,
+            factor,
+        }
+    }
+
+    fn factor(&self, rec: &HitRecord) -> f32 {
+        let c = self.factor.value_at(rec);
+        ((c.r + c.g + c.b) / 3.0).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let t = self.factor(rec);
+        let mut f = Color::black();
+        if t < 1.0 {
+            f += (1.0 - t) * self.first.eval(r_in, rec, direction);
+        }
+        if t > 0.0 {
+            f += t * self.second.eval(r_in, rec, direction);
+        }
+
+        f
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        let t = self.factor(rec);
+        let mut pdf = 0.0;
+        if t < 1.0 {
+            pdf += (1.0 - t) * self.first.pdf(r_in, rec, direction);
+        }
+        if t > 0.0 {
+            pdf += t * self.second.pdf(r_in, rec, direction);
+        }
+
+        pdf
+    }
+
+    // Picks one of the two with the probability it's blended in with, and
+    // weighs what it sampled by both, as the other could have picked it too.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let t = self.factor(rec);
+        let (chosen, other, p) = if sampler.random_float() < t {
+            (&self.second, &self.first, t)
+        } else {
+            (&self.first, &self.second, 1.0 - t)
+        };
+
+        let sample = chosen.sample(r_in, rec, sampler)?;
+        if sample.is_specular || p >= 1.0 {
+            return Some(sample);
+        }
+
+        let f =
+            p * sample.weight * sample.pdf + (1.0 - p) * other.eval(r_in, rec, &sample.direction);
+        let pdf = p * sample.pdf + (1.0 - p) * other.pdf(r_in, rec, &sample.direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            weight: f / pdf,
+            pdf,
+            ..sample
+        })
+    }
+
+    fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
+        let c = self.factor.value(uv, p);
+        let t = ((c.r + c.g + c.b) / 3.0).clamp(0.0, 1.0);
+        (1.0 - t) * self.first.emitted(uv, p) + t * self.second.emitted(uv, p)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.first.is_specular() && self.second.is_specular()
+    }
+
+    // There's only room for one medium inside, that of the material with the
+    // most say where the ray went in.
+    fn medium(&self, rec: &HitRecord) -> Option<Medium> {
+        if self.factor(rec) < 0.5 {
+            self.first.medium(rec)
+        } else {
+            self.second.medium(rec)
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{aabb::AABB, hittable::HitRecord, material::Material, ray::Ray};
+use crate::{aabb::AABB, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler};
 use cliffy::{Vec3, Vector};
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct MovingSphere {
     pub center0: Vec3,
@@ -8,7 +8,7 @@ pub struct MovingSphere {
     pub time0: f32,
     pub time1: f32,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl MovingSphere {
@@ -18,7 +18,7 @@ impl MovingSphere {
         time0: f32,
         time1: f32,
         radius: f32,
-        material: Rc<dyn Material>,
+        material: Arc<dyn Material>,
     ) -> Self {
         Self {
             center0,
@@ -35,7 +35,13 @@ impl MovingSphere {
             + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
         let oc = r.origin - self.center(r.time);
         let a = r.direction.mag_sq();
         let half_b = oc.dot(r.direction);
@@ -0,0 +1,639 @@
+use crate::{
+    color::Color,
+    dielectric::Dielectric,
+    diffuse_light::DiffuseLight,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    lambertian::Lambertian,
+    linear_bvh::LinearBVH,
+    material::Material,
+    metal::Metal,
+    texture::Texture,
+    triangle::{MeshVertices, TriangleMesh},
+};
+use cliffy::{Vec2, Vec3, Vector};
+use image::io::Reader as ImageReader;
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Loads Wavefront OBJ files, together with the MTL libraries they refer to.
+//
+// Polygons are split into triangle fans, and faces are grouped by material into
+// one `TriangleMesh` each, all sharing a single set of vertex buffers. Faces
+// without vertex normals are shaded flat, unless they are in a smoothing group
+// (`s 1`, ...), in which case normals are averaged over the faces of the group
+// that meet at each vertex. Groups and objects (`g`, `o`) are accepted but don't
+// affect the result, and other statements (lines, curves, ...) are ignored.
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, io::Error),
+    Image(PathBuf, image::ImageError),
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Image(path, e) => write!(f, "failed to load image {}: {}", path.display(), e),
+            Self::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// The subset of an MTL material that maps onto Parto's materials.
+struct MtlDesc {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ns: f32,
+    ni: f32,
+    dissolve: f32,
+    illum: u32,
+    map_kd: Option<PathBuf>,
+}
+
+impl Default for MtlDesc {
+    fn default() -> Self {
+        Self {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::black(),
+            ke: Color::black(),
+            ns: 0.0,
+            ni: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+            map_kd: None,
+        }
+    }
+}
+
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct Face {
+    corners: Vec<Corner>,
+    smoothing: u32,
+    material: usize,
+}
+
+// Where the normal of a mesh vertex comes from, which is part of what makes two
+// face corners the same vertex.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum NormalKey {
+    Given(usize),
+    Smooth(usize, u32),
+    Flat(usize),
+    Missing,
+}
+
+struct Parser {
+    path: PathBuf,
+    line: usize,
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    faces: Vec<Face>,
+    library: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<PathBuf, Arc<Texture>>,
+    // Materials in the order they are first used, index 0 is the default one.
+    materials: Vec<Arc<dyn Material>>,
+    material_indices: HashMap<String, usize>,
+    current_material: usize,
+    smoothing: u32,
+}
+
+impl Parser {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Syntax {
+            path: self.path.clone(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn floats<const N: usize>(&self, args: &[&str], min: usize) -> Result<[f32; N], ObjError> {
+        if args.len() < min || args.len() > N {
+            return Err(self.error(format!(
+                "expected {} to {} numbers, found {}",
+                min,
+                N,
+                args.len()
+            )));
+        }
+
+        let mut values = [0.0; N];
+        for (value, arg) in values.iter_mut().zip(args) {
+            *value = arg
+                .parse()
+                .map_err(|_| self.error(format!("`{}` is not a number", arg)))?;
+        }
+
+        Ok(values)
+    }
+
+    fn color(&self, args: &[&str]) -> Result<Color, ObjError> {
+        // A single value is a grey, `spectral` and `xyz` colors aren't supported.
+        let [r, g, b] = self.floats::<3>(args, 1)?;
+        if args.len() == 1 {
+            Ok(Color::new(r, r, r))
+        } else if args.len() == 3 {
+            Ok(Color::new(r, g, b))
+        } else {
+            Err(self.error("expected 1 or 3 color components".to_string()))
+        }
+    }
+
+    // OBJ indices start at 1, and negative ones count back from the last element
+    // defined so far.
+    fn index(&self, arg: &str, count: usize, kind: &str) -> Result<usize, ObjError> {
+        let i: i64 = arg
+            .parse()
+            .map_err(|_| self.error(format!("`{}` is not a valid {} index", arg, kind)))?;
+
+        let index = if i > 0 {
+            i - 1
+        } else if i < 0 {
+            count as i64 + i
+        } else {
+            -1
+        };
+
+        if index < 0 || index >= count as i64 {
+            return Err(self.error(format!(
+                "{} index {} is out of range, {} defined so far",
+                kind, i, count
+            )));
+        }
+
+        Ok(index as usize)
+    }
+
+    fn corner(&self, arg: &str) -> Result<Corner, ObjError> {
+        let mut parts = arg.split('/');
+        let position = self.index(parts.next().unwrap_or(""), self.positions.len(), "vertex")?;
+        let uv = match parts.next() {
+            Some(s) if !s.is_empty() => {
+                Some(self.index(s, self.uvs.len(), "texture coordinate")?)
+            }
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(s) if !s.is_empty() => Some(self.index(s, self.normals.len(), "normal")?),
+            _ => None,
+        };
+        if parts.next().is_some() {
+            return Err(self.error(format!("malformed face vertex `{}`", arg)));
+        }
+
+        Ok(Corner {
+            position,
+            uv,
+            normal,
+        })
+    }
+
+    fn parse(&mut self, text: &str) -> Result<(), ObjError> {
+        for (line, args) in logical_lines(text) {
+            self.line = line;
+            let (keyword, args) = match args.split_first() {
+                Some((keyword, args)) => (*keyword, args),
+                None => continue,
+            };
+
+            match keyword {
+                "v" => {
+                    // An optional `w`, or the vertex colors some exporters write,
+                    // are ignored.
+                    let [x, y, z, ..] = self.floats::<7>(args, 3)?;
+                    self.positions.push(Vec3::new(x, y, z));
+                }
+                "vt" => {
+                    let [u, v, _] = self.floats::<3>(args, 1)?;
+                    self.uvs.push(Vec2::new(u, v));
+                }
+                "vn" => {
+                    let [x, y, z] = self.floats::<3>(args, 3)?;
+                    self.normals.push(Vec3::new(x, y, z));
+                }
+                "f" => {
+                    if args.len() < 3 {
+                        return Err(self.error("a face needs at least three vertices".to_string()));
+                    }
+                    let corners = args
+                        .iter()
+                        .map(|arg| self.corner(arg))
+                        .collect::<Result<_, _>>()?;
+                    self.faces.push(Face {
+                        corners,
+                        smoothing: self.smoothing,
+                        material: self.current_material,
+                    });
+                }
+                "s" => {
+                    self.smoothing = match args.first() {
+                        Some(&"off") | None => 0,
+                        Some(s) => s
+                            .parse()
+                            .map_err(|_| self.error(format!("`{}` is not a smoothing group", s)))?,
+                    };
+                }
+                "mtllib" => {
+                    // File names may contain spaces, but so may the list of files.
+                    // Try the whole rest of the line first.
+                    let dir = self
+                        .path
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_default();
+                    let whole = dir.join(args.join(" "));
+                    if whole.is_file() {
+                        self.load_library(&whole)?;
+                    } else {
+                        for arg in args {
+                            self.load_library(&dir.join(arg))?;
+                        }
+                    }
+                }
+                "usemtl" => {
+                    let name = args.join(" ");
+                    self.current_material = match self.material_indices.get(&name) {
+                        Some(&index) => index,
+                        None => {
+                            let material = self.library.get(&name).cloned().ok_or_else(|| {
+                                self.error(format!("unknown material `{}`", name))
+                            })?;
+                            self.materials.push(material);
+                            self.material_indices.insert(name, self.materials.len() - 1);
+                            self.materials.len() - 1
+                        }
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_library(&mut self, path: &Path) -> Result<(), ObjError> {
+        let text = fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+
+        // Errors inside the library point at the library, not at the OBJ file.
+        let obj_path = std::mem::replace(&mut self.path, path.to_path_buf());
+        let obj_line = self.line;
+        let result = self.parse_library(&text);
+        self.path = obj_path;
+        self.line = obj_line;
+
+        result
+    }
+
+    fn parse_library(&mut self, text: &str) -> Result<(), ObjError> {
+        let mut current: Option<(String, MtlDesc)> = None;
+
+        for (line, args) in logical_lines(text) {
+            self.line = line;
+            let (keyword, args) = match args.split_first() {
+                Some((keyword, args)) => (*keyword, args),
+                None => continue,
+            };
+
+            if keyword == "newmtl" {
+                if let Some((name, desc)) = current.take() {
+                    let material = self.build_material(&desc)?;
+                    self.library.insert(name, material);
+                }
+                current = Some((args.join(" "), MtlDesc::default()));
+                continue;
+            }
+
+            let desc = match &mut current {
+                Some((_, desc)) => desc,
+                None => {
+                    return Err(self.error(format!("`{}` before the first newmtl", keyword)));
+                }
+            };
+
+            match keyword {
+                "Kd" => desc.kd = self.color(args)?,
+                "Ks" => desc.ks = self.color(args)?,
+                "Ke" => desc.ke = self.color(args)?,
+                "Ns" => desc.ns = self.floats::<1>(args, 1)?[0],
+                "Ni" => desc.ni = self.floats::<1>(args, 1)?[0],
+                "d" => desc.dissolve = self.floats::<1>(args, 1)?[0],
+                "Tr" => desc.dissolve = 1.0 - self.floats::<1>(args, 1)?[0],
+                "illum" => {
+                    desc.illum = args
+                        .first()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or_else(|| self.error("expected an illumination model".to_string()))?;
+                }
+                "map_Kd" => {
+                    // Options such as `-s 1 1 1` come before the file name, which
+                    // is taken to be the last argument.
+                    let file = args
+                        .last()
+                        .ok_or_else(|| self.error("expected a texture file".to_string()))?;
+                    let dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+                    desc.map_kd = Some(dir.join(file));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((name, desc)) = current {
+            let material = self.build_material(&desc)?;
+            self.library.insert(name, material);
+        }
+
+        Ok(())
+    }
+
+    fn texture(&mut self, path: &Path) -> Result<Arc<Texture>, ObjError> {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(texture.clone());
+        }
+
+        let image = ImageReader::open(path)
+            .map_err(|e| ObjError::Io(path.to_path_buf(), e))?
+            .decode()
+            .map_err(|e| ObjError::Image(path.to_path_buf(), e))?;
+        let texture = Arc::new(Texture::Image(image, Some(path.to_path_buf())));
+        self.textures.insert(path.to_path_buf(), texture.clone());
+
+        Ok(texture)
+    }
+
+    fn build_material(&mut self, desc: &MtlDesc) -> Result<Arc<dyn Material>, ObjError> {
+        let is_black = |c: &Color| c.r <= 0.0 && c.g <= 0.0 && c.b <= 0.0;
+
+        let material: Arc<dyn Material> = if !is_black(&desc.ke) {
+            Arc::new(DiffuseLight::with_color(desc.ke))
+        } else if matches!(desc.illum, 4 | 6 | 7 | 9) || desc.dissolve < 1.0 {
+            Arc::new(Dielectric::new(desc.ni))
+        } else if matches!(desc.illum, 3 | 5) && !is_black(&desc.ks) {
+            // Map the Phong exponent onto a fuzz, the sharper the highlight the
+            // smoother the metal.
+            let fuzz = (2.0 / (desc.ns.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(desc.ks, fuzz))
+        } else {
+            match &desc.map_kd {
+                Some(path) => Arc::new(Lambertian::new(self.texture(path)?)),
+                None => Arc::new(Lambertian::with_color(desc.kd)),
+            }
+        };
+
+        Ok(material)
+    }
+}
+
+// Splits the text into lines of whitespace separated words, with comments removed
+// and lines ending in a backslash joined with the next. Each line comes with its
+// (first) line number.
+fn logical_lines(text: &str) -> Vec<(usize, Vec<&str>)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, Vec<&str>)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let (line, continued) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let (_, words) = current.get_or_insert_with(|| (i + 1, Vec::new()));
+        words.extend(line.split_whitespace());
+        if !continued {
+            lines.extend(current.take());
+        }
+    }
+    lines.extend(current);
+
+    lines
+}
+
+// Turns the parsed faces into one mesh per material. Face corners that agree on
+// position, texture coordinates and normal become a single shared vertex.
+fn build_meshes(parser: Parser) -> Vec<TriangleMesh> {
+    let faces = &parser.faces;
+    let has_uvs = faces
+        .iter()
+        .flat_map(|f| &f.corners)
+        .any(|c| c.uv.is_some());
+    let has_normals = faces
+        .iter()
+        .any(|f| f.smoothing != 0 || f.corners.iter().any(|c| c.normal.is_some()));
+
+    let face_normal = |face: &Face| {
+        // Sum of the fan's triangle normals, which weighs them by area.
+        let p0 = parser.positions[face.corners[0].position];
+        let mut n = Vec3::zero();
+        for pair in face.corners[1..].windows(2) {
+            let p1 = parser.positions[pair[0].position];
+            let p2 = parser.positions[pair[1].position];
+            n += (p1 - p0).cross(p2 - p0);
+        }
+        n
+    };
+
+    // Accumulate the normals of every smoothing group at every position.
+    let mut smooth_normals: HashMap<(usize, u32), Vec3> = HashMap::new();
+    for face in faces.iter().filter(|f| f.smoothing != 0) {
+        let n = face_normal(face);
+        for corner in face.corners.iter().filter(|c| c.normal.is_none()) {
+            *smooth_normals
+                .entry((corner.position, face.smoothing))
+                .or_insert_with(Vec3::zero) += n;
+        }
+    }
+
+    let mut vertex_indices: HashMap<(usize, Option<usize>, NormalKey), usize> = HashMap::new();
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices: Vec<Vec<[usize; 3]>> = vec![Vec::new(); parser.materials.len()];
+
+    for (face_index, face) in faces.iter().enumerate() {
+        let mut face_vertices = Vec::with_capacity(face.corners.len());
+        for corner in &face.corners {
+            let normal_key = match corner.normal {
+                Some(n) => NormalKey::Given(n),
+                None if !has_normals => NormalKey::Missing,
+                None if face.smoothing != 0 => NormalKey::Smooth(corner.position, face.smoothing),
+                None => NormalKey::Flat(face_index),
+            };
+            let key = (corner.position, corner.uv, normal_key);
+
+            let index = *vertex_indices.entry(key).or_insert_with(|| {
+                positions.push(parser.positions[corner.position]);
+                if has_uvs {
+                    uvs.push(corner.uv.map_or(Vec2::zero(), |uv| parser.uvs[uv]));
+                }
+                if has_normals {
+                    let n = match normal_key {
+                        NormalKey::Given(n) => parser.normals[n],
+                        NormalKey::Smooth(p, s) => smooth_normals[&(p, s)],
+                        _ => face_normal(face),
+                    };
+                    normals.push(if n.mag_sq() > 0.0 { n.normalized() } else { n });
+                }
+                positions.len() - 1
+            });
+            face_vertices.push(index);
+        }
+
+        for pair in face_vertices[1..].windows(2) {
+            indices[face.material].push([face_vertices[0], pair[0], pair[1]]);
+        }
+    }
+
+    let vertices = Arc::new(MeshVertices::new(positions, normals, uvs));
+    parser
+        .materials
+        .iter()
+        .zip(indices)
+        .filter(|(_, indices)| !indices.is_empty())
+        .map(|(material, indices)| TriangleMesh::new(vertices.clone(), indices, material.clone()))
+        .collect()
+}
+
+// Loads the OBJ file at `path`. Faces that don't pick a material with `usemtl`
+// are a light grey Lambertian, and `material_override`, if given, replaces every
+// material in the file.
+pub fn load(
+    path: &Path,
+    material_override: Option<Arc<dyn Material>>,
+) -> Result<Hittable, ObjError> {
+    let text = fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+
+    let mut parser = Parser {
+        path: path.to_path_buf(),
+        line: 0,
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        faces: Vec::new(),
+        library: HashMap::new(),
+        textures: HashMap::new(),
+        materials: vec![Arc::new(Lambertian::with_color(Color::new(0.8, 0.8, 0.8)))],
+        material_indices: HashMap::new(),
+        current_material: 0,
+        smoothing: 0,
+    };
+    parser.parse(&text)?;
+
+    if parser.faces.is_empty() {
+        return Err(ObjError::Syntax {
+            path: path.to_path_buf(),
+            line: parser.line,
+            message: "the file has no faces".to_string(),
+        });
+    }
+
+    if let Some(material) = material_override {
+        parser.materials = vec![material];
+        parser.faces.iter_mut().for_each(|f| f.material = 0);
+    }
+
+    let mut meshes = build_meshes(parser);
+    if meshes.len() == 1 {
+        return Ok(Hittable::Mesh(meshes.remove(0)));
+    }
+
+    let mut list = HittableList::empty();
+    for mesh in meshes {
+        list.add(Arc::new(Hittable::Mesh(mesh)));
+    }
+    Ok(Hittable::Bvh(LinearBVH::new(&mut list, 0.0, 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Loads `text` as an OBJ file, written to a temporary file named after `name`.
+    fn load_text(name: &str, text: &str) -> Result<Hittable, ObjError> {
+        let path = std::env::temp_dir().join(format!("parto-test-{}.obj", name));
+        fs::write(&path, text).unwrap();
+        let result = load(&path, None);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn load_mesh(name: &str, text: &str) -> TriangleMesh {
+        match load_text(name, text) {
+            Ok(Hittable::Mesh(mesh)) => mesh,
+            Ok(_) => panic!("expected a single mesh"),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn positions(mesh: &TriangleMesh) -> Vec<[f32; 3]> {
+        mesh.vertices
+            .positions
+            .iter()
+            .map(|p| [p.x, p.y, p.z])
+            .collect()
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let relative = load_mesh(
+            "negative",
+            "v 0 0 0\nv 1 0 0\nv 9 9 9\nv 0 1 0\nf -4 -3 -1\n",
+        );
+        let absolute = load_mesh("positive", "v 0 0 0\nv 1 0 0\nv 9 9 9\nv 0 1 0\nf 1 2 4\n");
+
+        assert_eq!(relative.indices, vec![[0, 1, 2]]);
+        assert_eq!(relative.indices, absolute.indices);
+        assert_eq!(positions(&relative), positions(&absolute));
+        assert_eq!(
+            positions(&relative),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn polygons_are_split_into_fans() {
+        let mesh = load_mesh(
+            "polygon",
+            "v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 2 0\nv 0 1 0\nf 1 2 3 4 5\n",
+        );
+
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        for (name, face) in &[
+            ("past-end", "f 1 2 4"),
+            ("before-start", "f -4 1 2"),
+            ("zero", "f 0 1 2"),
+        ] {
+            match load_text(name, &format!("v 0 0 0\nv 1 0 0\nv 0 1 0\n{}\n", face)) {
+                Err(ObjError::Syntax { line, .. }) => assert_eq!(line, 4),
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("`{}` was accepted", face),
+            }
+        }
+    }
+}
@@ -1,24 +1,24 @@
-use crate::utilities::{self, random_int};
+use crate::sampler::Sampler;
 use cliffy::{Vec3, Vector};
 
-const POINT_COUNT: usize = 256;
+pub const POINT_COUNT: usize = 256;
 pub struct Perlin {
-    ran_vec: Vec<Vec3>,
-    perm_x: Vec<u32>,
-    perm_y: Vec<u32>,
-    perm_z: Vec<u32>,
+    pub ran_vec: Vec<Vec3>,
+    pub perm_x: Vec<u32>,
+    pub perm_y: Vec<u32>,
+    pub perm_z: Vec<u32>,
 }
 
 impl Perlin {
-    pub fn new() -> Self {
+    pub fn new(sampler: &mut Sampler) -> Self {
         let mut ran_vec = Vec::new();
         for _ in 0..POINT_COUNT {
-            ran_vec.push(utilities::random_vec3_between(-1.0, 1.0));
+            ran_vec.push(sampler.random_vec3_between(-1.0, 1.0));
         }
 
-        let perm_x = Self::generate_perm();
-        let perm_y = Self::generate_perm();
-        let perm_z = Self::generate_perm();
+        let perm_x = Self::generate_perm(sampler);
+        let perm_y = Self::generate_perm(sampler);
+        let perm_z = Self::generate_perm(sampler);
 
         Self {
             ran_vec,
@@ -78,21 +78,21 @@ impl Perlin {
         accum
     }
 
-    fn generate_perm() -> Vec<u32> {
+    fn generate_perm(sampler: &mut Sampler) -> Vec<u32> {
         let mut p = Vec::new();
 
         for i in 0..POINT_COUNT {
             p.push(i as u32);
         }
 
-        Self::permute(&mut p, POINT_COUNT);
+        Self::permute(&mut p, POINT_COUNT, sampler);
 
         p
     }
 
-    fn permute(p: &mut Vec<u32>, n: usize) {
+    fn permute(p: &mut Vec<u32>, n: usize, sampler: &mut Sampler) {
         for i in (0..n).rev() {
-            let target = random_int(0, i as i32) as usize;
+            let target = sampler.random_int(0, i as i32) as usize;
             let tmp = p[i];
             p[i] = p[target];
             p[target] = tmp;
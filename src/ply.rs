@@ -0,0 +1,521 @@
+use crate::{
+    color::Color,
+    lambertian::Lambertian,
+    material::Material,
+    texture::Texture,
+    triangle::{MeshVertices, TriangleMesh},
+};
+use cliffy::{Vec2, Vec3};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::SplitAsciiWhitespace,
+    sync::Arc,
+};
+
+// Reads Stanford PLY meshes, in ASCII or in either binary byte order.
+//
+// The `vertex` element provides positions (`x`, `y`, `z`) and optionally
+// normals (`nx`, `ny`, `nz`), texture coordinates (`u`/`v`, `s`/`t` or
+// `texture_u`/`texture_v`) and colors (`red`, `green`, `blue`, as bytes or as
+// floats between 0 and 1). The `face` element holds lists of vertex indices;
+// triangles are used as they are and larger polygons split into fans. Any other
+// element is read and skipped.
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(PathBuf, io::Error),
+    Format(PathBuf, String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Format(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Self::I8),
+            "uchar" | "uint8" => Some(Self::U8),
+            "short" | "int16" => Some(Self::I16),
+            "ushort" | "uint16" => Some(Self::U16),
+            "int" | "int32" => Some(Self::I32),
+            "uint" | "uint32" => Some(Self::U32),
+            "float" | "float32" => Some(Self::F32),
+            "double" | "float64" => Some(Self::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    // Scale that takes a color channel of this type to [0, 1].
+    fn color_scale(self) -> f32 {
+        match self {
+            Self::U8 | Self::I8 => 1.0 / 255.0,
+            Self::U16 | Self::I16 => 1.0 / 65535.0,
+            Self::U32 | Self::I32 => 1.0 / u32::MAX as f32,
+            Self::F32 | Self::F64 => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar(ScalarType, String),
+    List(ScalarType, ScalarType, String),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, names: &[&str]) -> Option<(usize, ScalarType)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(i, p)| match p {
+                Property::Scalar(ty, name) if names.contains(&name.as_str()) => Some((i, *ty)),
+                _ => None,
+            })
+    }
+
+    fn list(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|p| match p {
+            Property::List(_, _, name) => names.contains(&name.as_str()),
+            _ => false,
+        })
+    }
+}
+
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+}
+
+fn parse_header(text: &str) -> Result<Header, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::LittleEndian,
+                    "binary_big_endian" => Encoding::BigEndian,
+                    _ => return Err(format!("unknown format `{}`", format)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("bad element count `{}`", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| "property before the first element".to_string())?;
+                let count_type = ScalarType::parse(count_type)
+                    .ok_or_else(|| format!("unknown type `{}`", count_type))?;
+                let item_type = ScalarType::parse(item_type)
+                    .ok_or_else(|| format!("unknown type `{}`", item_type))?;
+                element
+                    .properties
+                    .push(Property::List(count_type, item_type, name.to_string()));
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| "property before the first element".to_string())?;
+                let ty = ScalarType::parse(ty).ok_or_else(|| format!("unknown type `{}`", ty))?;
+                element
+                    .properties
+                    .push(Property::Scalar(ty, name.to_string()));
+            }
+            ["end_header"] => {
+                let encoding = encoding.ok_or_else(|| "missing format line".to_string())?;
+                return Ok(Header { encoding, elements });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("unexpected header line `{}`", line.trim())),
+        }
+    }
+
+    Err("missing end_header".to_string())
+}
+
+// The data after the header, read one value at a time.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String> {
+        match self {
+            Self::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| "unexpected end of file".to_string())?;
+                if ty.is_float() {
+                    word.parse()
+                        .map_err(|_| format!("`{}` is not a number", word))
+                } else {
+                    word.parse::<i64>()
+                        .map(|i| i as f64)
+                        .map_err(|_| format!("`{}` is not an integer", word))
+                }
+            }
+            Self::Binary { data, big_endian } => {
+                let size = ty.size();
+                if data.len() < size {
+                    return Err("unexpected end of file".to_string());
+                }
+                let mut bytes = [0u8; 8];
+                bytes[..size].copy_from_slice(&data[..size]);
+                if *big_endian {
+                    bytes[..size].reverse();
+                }
+                *data = &data[size..];
+
+                // Everything is little endian from here on.
+                let value = match ty {
+                    ScalarType::I8 => bytes[0] as i8 as f64,
+                    ScalarType::U8 => bytes[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::I32 => {
+                        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    ScalarType::U32 => {
+                        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    ScalarType::F32 => {
+                        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    ScalarType::F64 => f64::from_le_bytes(bytes),
+                };
+                Ok(value)
+            }
+        }
+    }
+
+    // Reads one element, scalars into `scalars` and lists into `lists`, both in
+    // property order.
+    fn read_element(
+        &mut self,
+        element: &Element,
+        scalars: &mut Vec<f64>,
+        lists: &mut Vec<Vec<f64>>,
+    ) -> Result<(), String> {
+        scalars.clear();
+        lists.clear();
+        for property in &element.properties {
+            match property {
+                Property::Scalar(ty, _) => scalars.push(self.read(*ty)?),
+                Property::List(count_type, item_type, _) => {
+                    let count = self.read(*count_type)?;
+                    if count < 0.0 {
+                        return Err("negative list length".to_string());
+                    }
+                    let items = (0..count as usize)
+                        .map(|_| self.read(*item_type))
+                        .collect::<Result<_, _>>()?;
+                    lists.push(items);
+                    // Keep the scalar indices in step with the properties.
+                    scalars.push(0.0);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_mesh(data: &[u8]) -> Result<(MeshVertices, Vec<[usize; 3]>), String> {
+    const END: &[u8] = b"end_header";
+    let end = data
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| "missing end_header".to_string())?;
+    // The body starts after the line break that ends the header.
+    let body_start = data[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |i| end + i + 1);
+    let header_text = std::str::from_utf8(&data[..body_start])
+        .map_err(|_| "the header is not text".to_string())?;
+    let header = parse_header(header_text)?;
+
+    let mut body = match header.encoding {
+        Encoding::Ascii => Body::Ascii(
+            std::str::from_utf8(&data[body_start..])
+                .map_err(|_| "the ASCII body is not text".to_string())?
+                .split_ascii_whitespace(),
+        ),
+        Encoding::LittleEndian | Encoding::BigEndian => Body::Binary {
+            data: &data[body_start..],
+            big_endian: header.encoding == Encoding::BigEndian,
+        },
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces: Vec<Vec<f64>> = Vec::new();
+    let mut scalars = Vec::new();
+    let mut lists = Vec::new();
+
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let find = |names: &[&str]| element.scalar(names);
+                let (x, y, z) = match (find(&["x"]), find(&["y"]), find(&["z"])) {
+                    (Some(x), Some(y), Some(z)) => (x.0, y.0, z.0),
+                    _ => return Err("vertices without x, y and z".to_string()),
+                };
+                let normal = match (find(&["nx"]), find(&["ny"]), find(&["nz"])) {
+                    (Some(x), Some(y), Some(z)) => Some((x.0, y.0, z.0)),
+                    _ => None,
+                };
+                let uv = match (
+                    find(&["u", "s", "texture_u", "texture_s"]),
+                    find(&["v", "t", "texture_v", "texture_t"]),
+                ) {
+                    (Some(u), Some(v)) => Some((u.0, v.0)),
+                    _ => None,
+                };
+                let color = match (
+                    find(&["red", "r"]),
+                    find(&["green", "g"]),
+                    find(&["blue", "b"]),
+                ) {
+                    (Some(r), Some(g), Some(b)) => Some((r, g, b)),
+                    _ => None,
+                };
+
+                for _ in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut lists)?;
+                    let s = |i: usize| scalars[i] as f32;
+                    positions.push(Vec3::new(s(x), s(y), s(z)));
+                    if let Some((x, y, z)) = normal {
+                        normals.push(Vec3::new(s(x), s(y), s(z)));
+                    }
+                    if let Some((u, v)) = uv {
+                        uvs.push(Vec2::new(s(u), s(v)));
+                    }
+                    if let Some((r, g, b)) = color {
+                        colors.push(Color::new(
+                            s(r.0) * r.1.color_scale(),
+                            s(g.0) * g.1.color_scale(),
+                            s(b.0) * b.1.color_scale(),
+                        ));
+                    }
+                }
+            }
+            "face" => {
+                let list = element
+                    .list(&["vertex_indices", "vertex_index"])
+                    .ok_or_else(|| "faces without vertex_indices".to_string())?;
+                let list = element.properties[..list]
+                    .iter()
+                    .filter(|p| matches!(p, Property::List(..)))
+                    .count();
+                for _ in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut lists)?;
+                    faces.push(std::mem::take(&mut lists[list]));
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut lists)?;
+                }
+            }
+        }
+    }
+
+    let mut indices = Vec::new();
+    for (f, face) in faces.iter().enumerate() {
+        if face.len() < 3 {
+            return Err(format!("face {} has fewer than three vertices", f));
+        }
+        if let Some(i) = face.iter().find(|i| i.fract() != 0.0) {
+            return Err(format!("face {} has a vertex index of {}", f, i));
+        }
+        if let Some(i) = face
+            .iter()
+            .find(|&&i| i < 0.0 || i as usize >= positions.len())
+        {
+            return Err(format!(
+                "face {} uses vertex {}, but there are {}",
+                f,
+                i,
+                positions.len()
+            ));
+        }
+        for i in 2..face.len() {
+            indices.push([face[0] as usize, face[i - 1] as usize, face[i] as usize]);
+        }
+    }
+    if indices.is_empty() {
+        return Err("the file has no faces".to_string());
+    }
+
+    Ok((
+        MeshVertices::with_colors(positions, normals, uvs, colors),
+        indices,
+    ))
+}
+
+// Loads the PLY mesh at `path`. Without a `material`, meshes with vertex colors
+// get a Lambertian textured with them and all others a light grey one.
+pub fn load(path: &Path, material: Option<Arc<dyn Material>>) -> Result<TriangleMesh, PlyError> {
+    let data = fs::read(path).map_err(|e| PlyError::Io(path.to_path_buf(), e))?;
+    let (vertices, indices) =
+        read_mesh(&data).map_err(|message| PlyError::Format(path.to_path_buf(), message))?;
+
+    let material = material.unwrap_or_else(|| {
+        if vertices.colors.is_empty() {
+            Arc::new(Lambertian::with_color(Color::new(0.8, 0.8, 0.8)))
+        } else {
+            Arc::new(Lambertian::new(Arc::new(Texture::VertexColor)))
+        }
+    });
+
+    Ok(TriangleMesh::new(Arc::new(vertices), indices, material))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.5], [0.0, 2.0, -1.0]];
+    const COLORS: [[u8; 3]; 3] = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+
+    fn header(format: &str) -> String {
+        format!(
+            "ply\nformat {} 1.0\ncomment one triangle\nelement vertex 3\n\
+             property float x\nproperty float y\nproperty float z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\n\
+             element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+            format
+        )
+    }
+
+    fn ascii() -> Vec<u8> {
+        let mut text = header("ascii");
+        for (p, c) in POSITIONS.iter().zip(&COLORS) {
+            text += &format!("{} {} {} {} {} {}\n", p[0], p[1], p[2], c[0], c[1], c[2]);
+        }
+        text += "3 0 1 2\n";
+        text.into_bytes()
+    }
+
+    fn binary(
+        format: &str,
+        f32_bytes: fn(f32) -> [u8; 4],
+        i32_bytes: fn(i32) -> [u8; 4],
+    ) -> Vec<u8> {
+        let mut data = header(format).into_bytes();
+        for (p, c) in POSITIONS.iter().zip(&COLORS) {
+            p.iter().for_each(|&x| data.extend(f32_bytes(x)));
+            data.extend(c);
+        }
+        data.push(3);
+        (0..3).for_each(|i| data.extend(i32_bytes(i)));
+        data
+    }
+
+    // Positions, colors and indices, as plain numbers.
+    type Mesh = (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<[usize; 3]>);
+
+    fn mesh(data: &[u8]) -> Mesh {
+        let (vertices, indices) = read_mesh(data).unwrap();
+        let positions = vertices.positions.iter().map(|p| [p.x, p.y, p.z]).collect();
+        let colors = vertices.colors.iter().map(|c| [c.r, c.g, c.b]).collect();
+        (positions, colors, indices)
+    }
+
+    #[test]
+    fn encodings_agree() {
+        let ascii = mesh(&ascii());
+        let little = mesh(&binary(
+            "binary_little_endian",
+            f32::to_le_bytes,
+            i32::to_le_bytes,
+        ));
+        let big = mesh(&binary(
+            "binary_big_endian",
+            f32::to_be_bytes,
+            i32::to_be_bytes,
+        ));
+
+        assert_eq!(ascii.0, POSITIONS.to_vec());
+        assert_eq!(
+            ascii.1,
+            vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        );
+        assert_eq!(ascii.2, vec![[0, 1, 2]]);
+        assert_eq!(ascii, little);
+        assert_eq!(ascii, big);
+    }
+
+    #[test]
+    fn fractional_indices_are_errors() {
+        let data = String::from_utf8(ascii())
+            .unwrap()
+            .replace("3 0 1 2", "3 0 1 1.7");
+        assert!(read_mesh(data.as_bytes()).is_err());
+
+        let data = data.replace("uchar int vertex_indices", "uchar float vertex_indices");
+        assert!(read_mesh(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    microfacet::{self, Ggx},
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+    utilities::Frame,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::{any::Any, f32::consts::PI, sync::Arc};
+
+// Sharpest highlight the specular lobes get, as a perfectly smooth one can't be
+// told apart from a mirror, which needs a material of its own.
+const MIN_ALPHA: f32 = 1e-3;
+
+// One material for most surfaces, after the "Physically Based Shading at Disney"
+// BRDF (Burley 2012), with glass-like transmission on top. Every parameter is a
+// texture; the ones that are single numbers between 0 and 1 read the average of
+// its channels.
+pub struct Principled {
+    pub base_color: Arc<Texture>,
+    // 0 for a dielectric, 1 for a metal tinted by the base color.
+    pub metallic: Arc<Texture>,
+    pub roughness: Arc<Texture>,
+    // Strength of the reflection off dielectrics, 0.5 being that of a typical
+    // index of refraction of 1.5.
+    pub specular: Arc<Texture>,
+    // How much of the base color that reflection takes on.
+    pub specular_tint: Arc<Texture>,
+    // Soft reflection towards the edges, as off cloth.
+    pub sheen: Arc<Texture>,
+    pub sheen_tint: Arc<Texture>,
+    // A second, clear specular layer on top, as on car paint.
+    pub clearcoat: Arc<Texture>,
+    pub clearcoat_gloss: Arc<Texture>,
+    // How much light goes through, refracted like glass and tinted by the base
+    // color, instead of being scattered diffusely.
+    pub transmission: Arc<Texture>,
+    pub ir: f32, // Index of Refraction, for transmission
+    pub emission: Arc<Texture>,
+}
+
+// The parameters where a ray hit.
+struct Parameters {
+    base_color: Color,
+    metallic: f32,
+    roughness: f32,
+    specular: f32,
+    specular_tint: f32,
+    sheen: f32,
+    sheen_tint: f32,
+    clearcoat: f32,
+    clearcoat_gloss: f32,
+    transmission: f32,
+}
+
+// How much of the light each lobe scatters, which is also how likely it is to
+// be sampled.
+struct Lobes {
+    diffuse: f32,
+    specular: f32,
+    glass: f32,
+    clearcoat: f32,
+}
+
+impl Principled {
+    // A rough, white dielectric, with the other parameters set so as to not get
+    // in the way.
+    pub fn new(base_color: Arc<Texture>) -> Self {
+        let value = |v: f32| Arc::new(Texture::SolidColor(Color::new(v, v, v)));
+        Self {
+            base_color,
+            metallic: value(0.0),
+            roughness: value(0.5),
+            specular: value(0.5),
+            specular_tint: value(0.0),
+            sheen: value(0.0),
+            sheen_tint: value(0.5),
+            clearcoat: value(0.0),
+            clearcoat_gloss: value(1.0),
+            transmission: value(0.0),
+            ir: 1.5,
+            emission: value(0.0),
+        }
+    }
+
+    // Every texture it reads from.
+    pub fn textures(&self) -> [&Arc<Texture>; 11] {
+        [
+            &self.base_color,
+            &self.metallic,
+            &self.roughness,
+            &self.specular,
+            &self.specular_tint,
+            &self.sheen,
+            &self.sheen_tint,
+            &self.clearcoat,
+            &self.clearcoat_gloss,
+            &self.transmission,
+            &self.emission,
+        ]
+    }
+
+    fn parameters(&self, rec: &HitRecord) -> Parameters {
+        let scalar = |texture: &Texture| {
+            let c = texture.value_at(rec);
+            ((c.r + c.g + c.b) / 3.0).clamp(0.0, 1.0)
+        };
+
+        Parameters {
+            base_color: self.base_color.value_at(rec),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            specular_tint: scalar(&self.specular_tint),
+            sheen: scalar(&self.sheen),
+            sheen_tint: scalar(&self.sheen_tint),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_gloss: scalar(&self.clearcoat_gloss),
+            transmission: scalar(&self.transmission),
+        }
+    }
+
+    // How much denser the side the ray goes into is than the one it comes from.
+    fn eta(&self, rec: &HitRecord) -> f32 {
+        if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        }
+    }
+
+    // The BSDF times the cosine, and the density of sampling `wi`, summed over
+    // the lobes.
+    fn eval_local(&self, p: &Parameters, wo: &Vec3, wi: &Vec3, eta: f32) -> (Color, f32) {
+        let lobes = Lobes::new(p);
+        let total = lobes.total();
+        let mut f = Color::black();
+        let mut pdf = 0.0;
+
+        if wi.z > 0.0 {
+            let h = (*wo + *wi).normalized();
+
+            if lobes.diffuse > 0.0 {
+                f += lobes.diffuse * diffuse(p, wo, wi, &h) * wi.z;
+                pdf += lobes.diffuse / total * wi.z / PI;
+            }
+
+            if lobes.specular > 0.0 {
+                let ggx = specular_distribution(p);
+                let fresnel = schlick(&specular_color(p), wo.dot(h));
+                f += lobes.specular * fresnel * (ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z));
+                pdf += lobes.specular / total * ggx.visible_normal_pdf(wo, &h) / (4.0 * wo.dot(h));
+            }
+
+            if lobes.clearcoat > 0.0 {
+                let ggx = clearcoat_distribution(p);
+                let fresnel = schlick(&Color::new(0.04, 0.04, 0.04), wo.dot(h));
+                f += lobes.clearcoat * fresnel * (ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z));
+                pdf += lobes.clearcoat / total * ggx.visible_normal_pdf(wo, &h) / (4.0 * wo.dot(h));
+            }
+        }
+
+        if lobes.glass > 0.0 {
+            let ggx = specular_distribution(p);
+            let tint = if wi.z < 0.0 {
+                p.base_color
+            } else {
+                Color::white()
+            };
+            f += lobes.glass * tint * microfacet::dielectric_eval(&ggx, wo, wi, eta);
+            pdf += lobes.glass / total * microfacet::dielectric_pdf(&ggx, wo, wi, eta);
+        }
+
+        (f, pdf)
+    }
+}
+
+impl Lobes {
+    fn new(p: &Parameters) -> Self {
+        let dielectric = 1.0 - p.metallic;
+        Self {
+            diffuse: dielectric * (1.0 - p.transmission),
+            // Transmissive parts reflect through the glass lobe instead.
+            specular: 1.0 - dielectric * p.transmission,
+            glass: dielectric * p.transmission,
+            clearcoat: 0.25 * p.clearcoat,
+        }
+    }
+
+    fn total(&self) -> f32 {
+        self.diffuse + self.specular + self.glass + self.clearcoat
+    }
+}
+
+impl Material for Principled {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 {
+            return Color::black();
+        }
+
+        self.eval_local(&self.parameters(rec), &wo, &wi, self.eta(rec))
+            .0
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+
+        self.eval_local(&self.parameters(rec), &wo, &wi, self.eta(rec))
+            .1
+    }
+
+    // Picks one lobe to sample, but weighs the direction by all of them, as any
+    // of them could have picked it.
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let p = self.parameters(rec);
+        let eta = self.eta(rec);
+        let lobes = Lobes::new(&p);
+        let mut u = sampler.random_float() * lobes.total();
+
+        let wi = if u < lobes.diffuse {
+            cosine_direction(sampler)
+        } else {
+            u -= lobes.diffuse;
+            if u < lobes.specular {
+                let m = specular_distribution(&p).sample_visible_normal(
+                    &wo,
+                    sampler.random_float(),
+                    sampler.random_float(),
+                );
+                microfacet::reflect(&wo, &m)
+            } else if u < lobes.specular + lobes.glass {
+                microfacet::dielectric_sample(&specular_distribution(&p), &wo, eta, sampler)?
+            } else {
+                let m = clearcoat_distribution(&p).sample_visible_normal(
+                    &wo,
+                    sampler.random_float(),
+                    sampler.random_float(),
+                );
+                microfacet::reflect(&wo, &m)
+            }
+        };
+
+        let (f, pdf) = self.eval_local(&p, &wo, &wi, eta);
+        if pdf <= 0.0 || f == Color::black() {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: f / pdf,
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn emitted(&self, uv: &Vec2, p: &Vec3) -> Color {
+        self.emission.value(uv, p)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Burley's diffuse, which darkens smooth surfaces and brightens rough ones
+// towards the edges, with the sheen added on.
+fn diffuse(p: &Parameters, wo: &Vec3, wi: &Vec3, h: &Vec3) -> Color {
+    let cos_d = wi.dot(*h);
+    let fd90 = 0.5 + 2.0 * p.roughness * cos_d * cos_d;
+    let fl = 1.0 + (fd90 - 1.0) * (1.0 - wi.z).powi(5);
+    let fv = 1.0 + (fd90 - 1.0) * (1.0 - wo.z).powi(5);
+
+    let sheen_color = lerp(&Color::white(), &tint(&p.base_color), p.sheen_tint);
+    let sheen = p.sheen * (1.0 - cos_d).powi(5) * sheen_color;
+
+    p.base_color * (fl * fv / PI) + sheen
+}
+
+fn specular_distribution(p: &Parameters) -> Ggx {
+    Ggx {
+        alpha: (p.roughness * p.roughness).max(MIN_ALPHA),
+    }
+}
+
+fn clearcoat_distribution(p: &Parameters) -> Ggx {
+    Ggx {
+        alpha: 0.1 + (MIN_ALPHA - 0.1) * p.clearcoat_gloss,
+    }
+}
+
+// Reflectance head on: that of a dielectric with the given `specular`,
+// blending into the base color as it turns to metal.
+fn specular_color(p: &Parameters) -> Color {
+    let dielectric =
+        0.08 * p.specular * lerp(&Color::white(), &tint(&p.base_color), p.specular_tint);
+    lerp(&dielectric, &p.base_color, p.metallic)
+}
+
+// The hue of `c` without its brightness.
+fn tint(c: &Color) -> Color {
+    let luminance = 0.3 * c.r + 0.6 * c.g + 0.1 * c.b;
+    if luminance > 0.0 {
+        *c / luminance
+    } else {
+        Color::white()
+    }
+}
+
+fn schlick(f0: &Color, cos: f32) -> Color {
+    *f0 + (Color::white() - *f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+fn lerp(a: &Color, b: &Color, t: f32) -> Color {
+    *a * (1.0 - t) + *b * t
+}
+
+fn cosine_direction(sampler: &mut Sampler) -> Vec3 {
+    let r = sampler.random_float().sqrt();
+    let phi = 2.0 * PI * sampler.random_float();
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - r * r).max(0.0).sqrt())
+}
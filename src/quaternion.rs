@@ -0,0 +1,84 @@
+use cliffy::{Vec3, Vector};
+
+// Rotations as unit quaternions, stored [x, y, z, w] like glTF does.
+pub type Quaternion = [f32; 4];
+
+pub fn identity() -> Quaternion {
+    [0.0, 0.0, 0.0, 1.0]
+}
+
+// Rotation by `degrees` counter-clockwise around `axis`, looking down it.
+pub fn from_axis_angle(axis: &Vec3, degrees: f32) -> Quaternion {
+    let half = degrees.to_radians() / 2.0;
+    let axis = axis.normalized() * half.sin();
+    [axis.x, axis.y, axis.z, half.cos()]
+}
+
+// Rotation around X, then Y, then Z, by the angles in degrees.
+pub fn from_euler(degrees: &Vec3) -> Quaternion {
+    mul(
+        mul(
+            from_axis_angle(&Vec3::new(0.0, 0.0, 1.0), degrees.z),
+            from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), degrees.y),
+        ),
+        from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), degrees.x),
+    )
+}
+
+// Like matrices, `mul(a, b)` rotates by `b` first.
+pub fn mul(a: Quaternion, b: Quaternion) -> Quaternion {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+fn dot(a: Quaternion, b: Quaternion) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
+}
+
+pub fn normalized(q: Quaternion) -> Quaternion {
+    let len = dot(q, q).sqrt();
+    [q[0] / len, q[1] / len, q[2] / len, q[3] / len]
+}
+
+// Angle in radians of the shortest rotation taking `a` to `b`.
+pub fn angle_between(a: Quaternion, b: Quaternion) -> f32 {
+    2.0 * dot(a, b).abs().min(1.0).acos()
+}
+
+// Turns from `a` to `b` at a constant rate as `t` goes from 0 to 1, the short
+// way around.
+pub fn slerp(a: Quaternion, b: Quaternion, t: f32) -> Quaternion {
+    let mut cos_theta = dot(a, b);
+    let b = if cos_theta < 0.0 {
+        cos_theta = -cos_theta;
+        [-b[0], -b[1], -b[2], -b[3]]
+    } else {
+        b
+    };
+
+    // Nearly the same rotation, where the angle can't be told precisely enough.
+    // A straight line between them is just as good.
+    let (wa, wb) = if cos_theta > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        (
+            ((1.0 - t) * theta).sin() / sin_theta,
+            (t * theta).sin() / sin_theta,
+        )
+    };
+
+    normalized([
+        wa * a[0] + wb * b[0],
+        wa * a[1] + wb * b[1],
+        wa * a[2] + wb * b[2],
+        wa * a[3] + wb * b[3],
+    ])
+}
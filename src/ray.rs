@@ -5,6 +5,8 @@ pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
     pub time: f32,
+    // The one wavelength, in nanometers, a ray carries when rendering spectrally.
+    pub wavelength: Option<f32>,
 }
 
 impl Ray {
@@ -17,6 +19,7 @@ impl Ray {
             origin,
             direction,
             time,
+            wavelength: None,
         }
     }
 
@@ -1,19 +1,19 @@
-use cliffy::{Vec2, Vec3};
+use cliffy::{Vec2, Vec3, Vector};
 
-use crate::{aabb::AABB, hittable::HitRecord, material::Material, ray::Ray};
-use std::rc::Rc;
+use crate::{aabb::AABB, hittable::HitRecord, material::Material, ray::Ray, sampler::Sampler};
+use std::sync::Arc;
 
 pub struct XYRect {
-    pub mp: Rc<dyn Material>,
+    pub mp: Arc<dyn Material>,
     pub x0: f32,
     pub x1: f32,
     pub y0: f32,
     pub y1: f32,
-    k: f32,
+    pub k: f32,
 }
 
 impl XYRect {
-    pub fn new(mp: Rc<dyn Material>, x0: f32, x1: f32, y0: f32, y1: f32, k: f32) -> Self {
+    pub fn new(mp: Arc<dyn Material>, x0: f32, x1: f32, y0: f32, y1: f32, k: f32) -> Self {
         Self {
             mp,
             x0,
@@ -24,7 +24,13 @@ impl XYRect {
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
         let t = (self.k - r.origin.z) / r.direction.z;
         if t < t_min || t > t_max {
             return None;
@@ -43,6 +49,8 @@ impl XYRect {
             (x - self.x0) / (self.x1 - self.x0),
             (y - self.y0) / (self.y1 - self.y0),
         );
+        rec.dpdu = Vec3::new(self.x1 - self.x0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, self.y1 - self.y0, 0.0);
         rec.t = t;
 
         let outward_normal = Vec3::new(0.0, 0.0, 1.0);
@@ -60,19 +68,37 @@ impl XYRect {
             &Vec3::new(self.x1, self.y1, self.k + 0.0001),
         ))
     }
+
+    // Density, over solid angle, of `random` heading from `origin` in direction
+    // `v`.
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        let hit = self.hit(&Ray::new(*origin, *v), 0.001, f32::INFINITY, sampler);
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        area_pdf_to_solid_angle(hit, v, area)
+    }
+
+    // A direction from `origin` towards a point picked uniformly on the rectangle.
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let point = Vec3::new(
+            sampler.random_float_between(self.x0, self.x1),
+            sampler.random_float_between(self.y0, self.y1),
+            self.k,
+        );
+        point - *origin
+    }
 }
 
 pub struct XZRect {
-    pub mp: Rc<dyn Material>,
+    pub mp: Arc<dyn Material>,
     pub x0: f32,
     pub x1: f32,
     pub z0: f32,
     pub z1: f32,
-    k: f32,
+    pub k: f32,
 }
 
 impl XZRect {
-    pub fn new(mp: Rc<dyn Material>, x0: f32, x1: f32, z0: f32, z1: f32, k: f32) -> Self {
+    pub fn new(mp: Arc<dyn Material>, x0: f32, x1: f32, z0: f32, z1: f32, k: f32) -> Self {
         Self {
             mp,
             x0,
@@ -83,7 +109,13 @@ impl XZRect {
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
         let t = (self.k - r.origin.y) / r.direction.y;
         if t < t_min || t > t_max {
             return None;
@@ -102,6 +134,8 @@ impl XZRect {
             (x - self.x0) / (self.x1 - self.x0),
             (z - self.z0) / (self.z1 - self.z0),
         );
+        rec.dpdu = Vec3::new(self.x1 - self.x0, 0.0, 0.0);
+        rec.dpdv = Vec3::new(0.0, 0.0, self.z1 - self.z0);
         rec.t = t;
 
         let outward_normal = Vec3::new(0.0, 1.0, 0.0);
@@ -119,19 +153,34 @@ impl XZRect {
             &Vec3::new(self.x1, self.k + 0.0001, self.z1),
         ))
     }
+
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        let hit = self.hit(&Ray::new(*origin, *v), 0.001, f32::INFINITY, sampler);
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        area_pdf_to_solid_angle(hit, v, area)
+    }
+
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let point = Vec3::new(
+            sampler.random_float_between(self.x0, self.x1),
+            self.k,
+            sampler.random_float_between(self.z0, self.z1),
+        );
+        point - *origin
+    }
 }
 
 pub struct YZRect {
-    pub mp: Rc<dyn Material>,
+    pub mp: Arc<dyn Material>,
     pub y0: f32,
     pub y1: f32,
     pub z0: f32,
     pub z1: f32,
-    k: f32,
+    pub k: f32,
 }
 
 impl YZRect {
-    pub fn new(mp: Rc<dyn Material>, y0: f32, y1: f32, z0: f32, z1: f32, k: f32) -> Self {
+    pub fn new(mp: Arc<dyn Material>, y0: f32, y1: f32, z0: f32, z1: f32, k: f32) -> Self {
         Self {
             mp,
             y0,
@@ -142,7 +191,13 @@ impl YZRect {
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
         let t = (self.k - r.origin.x) / r.direction.x;
         if t < t_min || t > t_max {
             return None;
@@ -161,6 +216,8 @@ impl YZRect {
             (y - self.y0) / (self.y1 - self.y0),
             (z - self.z0) / (self.z1 - self.z0),
         );
+        rec.dpdu = Vec3::new(0.0, self.y1 - self.y0, 0.0);
+        rec.dpdv = Vec3::new(0.0, 0.0, self.z1 - self.z0);
         rec.t = t;
 
         let outward_normal = Vec3::new(1.0, 0.0, 0.0);
@@ -178,4 +235,33 @@ impl YZRect {
             &Vec3::new(self.k + 0.0001, self.y1, self.z1),
         ))
     }
+
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        let hit = self.hit(&Ray::new(*origin, *v), 0.001, f32::INFINITY, sampler);
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        area_pdf_to_solid_angle(hit, v, area)
+    }
+
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let point = Vec3::new(
+            self.k,
+            sampler.random_float_between(self.y0, self.y1),
+            sampler.random_float_between(self.z0, self.z1),
+        );
+        point - *origin
+    }
+}
+
+// Picking points uniformly on a surface of `area` makes the density of going in
+// direction `v` grow with the distance squared, and as the surface is seen more
+// edge on.
+fn area_pdf_to_solid_angle(hit: Option<HitRecord>, v: &Vec3, area: f32) -> f32 {
+    match hit {
+        Some(rec) => {
+            let distance_squared = rec.t * rec.t * v.mag_sq();
+            let cosine = (v.dot(rec.normal) / v.mag()).abs();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
 }
@@ -0,0 +1,437 @@
+use crate::{
+    camera::Camera, color::Color, hittable::HitRecord, hittable_list::HittableList, medium::Medium,
+    ray::Ray, sampler::Sampler, spectrum,
+};
+use cliffy::Vector;
+use image::{DynamicImage, GenericImage, Pixel};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc,
+};
+
+// Edge length of the square tiles the image is split into. Every pixel draws from
+// its own sampler, so the result doesn't depend on how many threads there are or
+// which one picked a tile up.
+const TILE_SIZE: u32 = 16;
+
+pub struct RenderSettings {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    pub background: Color,
+    pub threads: usize,
+    pub seed: u64,
+    // Traces one wavelength per sample, with RGB colors turned into spectra
+    // along the way, and turns the results back into RGB at the end.
+    pub spectral: bool,
+}
+
+struct Tile {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+#[inline]
+fn clamp(x: f32, min: f32, max: f32) -> f32 {
+    let mut result = x;
+
+    if x < min {
+        result = min;
+    } else if x > max {
+        result = max;
+    }
+
+    result
+}
+
+// Most random walks find their way out long before this many scattering events;
+// the few that don't are dropped.
+const MAX_SCATTERING_EVENTS: u32 = 256;
+
+// What rays are traced against.
+struct Scene<'a> {
+    world: &'a HittableList,
+    lights: &'a HittableList,
+    background: &'a Color,
+}
+
+pub fn ray_color(
+    r: &Ray,
+    background: &Color,
+    world: &HittableList,
+    lights: &HittableList,
+    depth: u32,
+    sampler: &mut Sampler,
+) -> Color {
+    let scene = Scene {
+        world,
+        lights,
+        background,
+    };
+    trace(r, &scene, depth, None, None, sampler)
+}
+
+// `scattering_pdf` is the density `r` was scattered in its direction with, when
+// the lights were sampled at its origin as well. Any light it runs into is then
+// weighed against what sampling them found already. `medium` is what `r` is
+// travelling through, if it's inside a surface that has one.
+fn trace(
+    r: &Ray,
+    scene: &Scene,
+    depth: u32,
+    scattering_pdf: Option<f32>,
+    medium: Option<Medium>,
+    sampler: &mut Sampler,
+) -> Color {
+    // If we've exceeded the ray bounce limit, no more light is gathered.
+    if depth == 0 {
+        return Color::black();
+    }
+
+    let walked;
+    let (r, hit, throughput) = match &medium {
+        Some(medium) => match walk(r, medium, scene.world, sampler) {
+            Some((ray, hit, throughput)) => {
+                walked = ray;
+                (&walked, hit, throughput)
+            }
+            None => return Color::black(),
+        },
+        None => (
+            r,
+            scene.world.hit(r, 0.001, f32::INFINITY, sampler),
+            Color::white(),
+        ),
+    };
+
+    let rec = match hit {
+        Some(rec) => rec,
+        None => return at_wavelength(&(throughput * *scene.background), r.wavelength),
+    };
+    let throughput = at_wavelength(&throughput, r.wavelength);
+
+    throughput * shade(r, &rec, scene, depth, scattering_pdf, medium, sampler)
+}
+
+// Follows `r` through `medium`, scattering it around until it reaches a surface.
+// Returns the last leg of the way, what it hit and what the light coming back
+// along it is multiplied by, or `None` if the walk went on for too long.
+fn walk(
+    r: &Ray,
+    medium: &Medium,
+    world: &HittableList,
+    sampler: &mut Sampler,
+) -> Option<(Ray, Option<HitRecord>, Color)> {
+    let mut ray = Ray::with_time(r.origin, r.direction.normalized(), r.time);
+    ray.wavelength = r.wavelength;
+    let mut throughput = Color::white();
+
+    for _ in 0..MAX_SCATTERING_EVENTS {
+        let hit = world.hit(&ray, 0.001, f32::INFINITY, sampler);
+        let max = hit.as_ref().map_or(f32::INFINITY, |rec| rec.t);
+        let (distance, weight) = medium.sample_distance(max, &throughput, sampler);
+        throughput *= weight;
+        if distance >= max || throughput == Color::black() {
+            return Some((ray, hit, throughput));
+        }
+
+        let direction = medium.sample_direction(&ray.direction, sampler);
+        ray = Ray {
+            origin: ray.at(distance),
+            direction,
+            ..ray
+        };
+    }
+
+    None
+}
+
+// Light leaving `rec` back along `r`.
+fn shade(
+    r: &Ray,
+    rec: &HitRecord,
+    scene: &Scene,
+    depth: u32,
+    scattering_pdf: Option<f32>,
+    medium: Option<Medium>,
+    sampler: &mut Sampler,
+) -> Color {
+    let mut emitted = at_wavelength(&rec.mat.emitted(&rec.uv, &rec.point), r.wavelength);
+    // Most surfaces don't glow, and then there's nothing to weigh.
+    if let (Some(pdf), false) = (scattering_pdf, emitted == Color::black()) {
+        let light_pdf = scene.lights.pdf_value(&r.origin, &r.direction, sampler);
+        emitted *= power_heuristic(pdf, light_pdf);
+    }
+
+    let sample = match rec.mat.sample(r, rec, sampler) {
+        Some(sample) => sample,
+        None => return emitted,
+    };
+    let weight = at_wavelength(&sample.weight, r.wavelength);
+
+    // Going through the surface enters what's inside it, or leaves it again.
+    let scattered_medium = if sample.direction.dot(rec.geometric_normal) < 0.0 {
+        if rec.front_face {
+            rec.mat.medium(rec)
+        } else {
+            None
+        }
+    } else {
+        medium
+    };
+
+    let mut scattered = Ray::with_time(rec.point, sample.direction, r.time);
+    scattered.wavelength = r.wavelength;
+    // Shadow rays don't know about media, so lights aren't sampled from inside
+    // one. Materials with both specular and other parts get them sampled even
+    // when `sample` went for a specular direction, as the other parts' share
+    // of the light would go missing otherwise.
+    let lights_sampled = !rec.mat.is_specular() && !scene.lights.objects.is_empty();
+    if !lights_sampled || medium.is_some() {
+        let ray_color = trace(
+            &scattered,
+            scene,
+            depth - 1,
+            None,
+            scattered_medium,
+            sampler,
+        );
+        return emitted + weight * ray_color;
+    }
+
+    let direct = sample_lights(r, rec, scene.world, scene.lights, sampler);
+    // Only directions `pdf` knows about could have been picked by light sampling
+    // as well, and only if the ray goes straight to what it hits rather than
+    // wandering through a medium first.
+    let scattering_pdf = if sample.is_specular || scattered_medium.is_some() {
+        None
+    } else {
+        Some(sample.pdf)
+    };
+    let ray_color = trace(
+        &scattered,
+        scene,
+        depth - 1,
+        scattering_pdf,
+        scattered_medium,
+        sampler,
+    );
+
+    emitted + direct + weight * ray_color
+}
+
+// Light arriving straight from a point picked on one of the lights, if nothing is
+// in the way.
+fn sample_lights(
+    r: &Ray,
+    rec: &HitRecord,
+    world: &HittableList,
+    lights: &HittableList,
+    sampler: &mut Sampler,
+) -> Color {
+    let direction = lights.random(&rec.point, sampler);
+    let light_pdf = lights.pdf_value(&rec.point, &direction, sampler);
+    if light_pdf <= 0.0 {
+        return Color::black();
+    }
+
+    // Shadow rays don't know about media, so none are sent into one.
+    let enters_medium = rec.front_face
+        && direction.dot(rec.geometric_normal) < 0.0
+        && rec.mat.medium(rec).is_some();
+    if enters_medium {
+        return Color::black();
+    }
+
+    let f = at_wavelength(&rec.mat.eval(r, rec, &direction), r.wavelength);
+    if f == Color::black() {
+        return Color::black();
+    }
+
+    let shadow_ray = Ray::with_time(rec.point, direction, r.time);
+    match world.hit(&shadow_ray, 0.001, f32::INFINITY, sampler) {
+        Some(light) => {
+            let emitted = at_wavelength(&light.mat.emitted(&light.uv, &light.point), r.wavelength);
+            let weight = power_heuristic(light_pdf, rec.mat.pdf(r, rec, &direction));
+            f * emitted * (weight / light_pdf)
+        }
+        None => Color::black(),
+    }
+}
+
+// `c` as seen by a ray of `wavelength`, as a gray, or as it is without one.
+fn at_wavelength(c: &Color, wavelength: Option<f32>) -> Color {
+    match wavelength {
+        Some(lambda) => {
+            let value = spectrum::upsample(c, lambda);
+            Color::new(value, value, value)
+        }
+        None => *c,
+    }
+}
+
+// How much to trust a sample drawn with density `pdf` when another strategy,
+// with density `other_pdf`, could have drawn it as well.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+fn write_color(
+    image: &mut DynamicImage,
+    x: u32,
+    y: u32,
+    pixel_color: &Color,
+    samples_per_pixel: u32,
+) {
+    let mut r = pixel_color.r;
+    let mut g = pixel_color.g;
+    let mut b = pixel_color.b;
+
+    // Divide the color by the number of samples and gamma-correct for gamma=2.0.
+    let scale = 1.0 / samples_per_pixel as f32;
+    r = (r * scale).sqrt();
+    g = (g * scale).sqrt();
+    b = (b * scale).sqrt();
+
+    let ur = (256.0 * clamp(r, 0.0, 0.999)) as u8;
+    let ug = (256.0 * clamp(g, 0.0, 0.999)) as u8;
+    let ub = (256.0 * clamp(b, 0.0, 0.999)) as u8;
+
+    let pixel = Pixel::from_channels(ur, ug, ub, 255);
+    image.put_pixel(x, y, pixel);
+}
+
+fn make_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    for y0 in (0..height).step_by(TILE_SIZE as usize) {
+        for x0 in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x0,
+                y0,
+                x1: (x0 + TILE_SIZE).min(width),
+                y1: (y0 + TILE_SIZE).min(height),
+            });
+        }
+    }
+
+    tiles
+}
+
+fn render_tile(
+    tile: &Tile,
+    world: &HittableList,
+    lights: &HittableList,
+    cam: &Camera,
+    settings: &RenderSettings,
+) -> Vec<Color> {
+    let width = settings.image_width;
+    let height = settings.image_height;
+    let mut pixels = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+    let white = spectrum::white();
+
+    for y in tile.y0..tile.y1 {
+        // Image rows go top to bottom, the camera's v axis bottom to top.
+        let j = height - 1 - y;
+        for i in tile.x0..tile.x1 {
+            let mut sampler = Sampler::for_pixel(settings.seed, i, y);
+            let mut pixel_color = Color::black();
+            for s in 0..settings.samples_per_pixel {
+                let u = (i as f32 + sampler.random_float()) / (width - 1) as f32;
+                let v = (j as f32 + sampler.random_float()) / (height - 1) as f32;
+                let mut r = cam.get_ray(u, v, &mut sampler);
+                if !settings.spectral {
+                    pixel_color += ray_color(
+                        &r,
+                        &settings.background,
+                        world,
+                        lights,
+                        settings.max_depth,
+                        &mut sampler,
+                    );
+                    continue;
+                }
+
+                // The samples of a pixel split the visible range between them,
+                // so that together they cover it evenly.
+                let lambda = spectrum::LAMBDA_MIN
+                    + (s as f32 + sampler.random_float()) / settings.samples_per_pixel as f32
+                        * (spectrum::LAMBDA_MAX - spectrum::LAMBDA_MIN);
+                r.wavelength = Some(lambda);
+                let radiance = ray_color(
+                    &r,
+                    &settings.background,
+                    world,
+                    lights,
+                    settings.max_depth,
+                    &mut sampler,
+                );
+                pixel_color += spectrum::wavelength_to_rgb(lambda, radiance.g, &white);
+            }
+            pixels.push(pixel_color);
+        }
+    }
+
+    pixels
+}
+
+pub fn render(
+    world: &HittableList,
+    lights: &HittableList,
+    cam: &Camera,
+    settings: &RenderSettings,
+) -> DynamicImage {
+    let mut image = DynamicImage::new_rgb8(settings.image_width, settings.image_height);
+    let tiles = make_tiles(settings.image_width, settings.image_height);
+    let next_tile = AtomicUsize::new(0);
+    let threads = settings.threads.max(1).min(tiles.len().max(1));
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let tiles = &tiles;
+            let next_tile = &next_tile;
+            scope.spawn(move || loop {
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                if index >= tiles.len() {
+                    break;
+                }
+
+                let pixels = render_tile(&tiles[index], world, lights, cam, settings);
+                if sender.send((index, pixels)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut done = 0;
+        for (index, pixels) in receiver {
+            let tile = &tiles[index];
+            let mut pixel = pixels.iter();
+            for y in tile.y0..tile.y1 {
+                for x in tile.x0..tile.x1 {
+                    write_color(
+                        &mut image,
+                        x,
+                        y,
+                        pixel.next().unwrap(),
+                        settings.samples_per_pixel,
+                    );
+                }
+            }
+
+            done += 1;
+            eprint!("\rTiles remaining: {} ", tiles.len() - done);
+        }
+        eprintln!();
+    });
+
+    image
+}
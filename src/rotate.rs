@@ -2,19 +2,21 @@ use crate::{
     aabb::AABB,
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    sampler::Sampler,
 };
 use cliffy::Vec3;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct RotateY {
-    pub ptr: Rc<Hittable>,
+    pub ptr: Arc<Hittable>,
+    pub angle: f32,
     pub sin_theta: f32,
     pub cos_theta: f32,
     pub bbox: Option<AABB>,
 }
 
 impl RotateY {
-    pub fn new(ptr: Rc<Hittable>, angle: f32) -> Self {
+    pub fn new(ptr: Arc<Hittable>, angle: f32) -> Self {
         let radians = angle.to_radians();
         let sin_theta = radians.sin();
         let cos_theta = radians.cos();
@@ -48,13 +50,14 @@ impl RotateY {
 
         Self {
             ptr,
+            angle,
             sin_theta,
             cos_theta,
             bbox,
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         let mut origin = r.origin;
         let mut direction = r.direction;
 
@@ -66,18 +69,23 @@ impl RotateY {
 
         let rotated_r = Ray::with_time(origin, direction, r.time);
 
-        if let Some(mut rec) = self.ptr.hit(&rotated_r, t_min, t_max) {
-            let mut p = rec.point;
-            let mut normal = rec.normal;
+        if let Some(mut rec) = self.ptr.hit(&rotated_r, t_min, t_max, sampler) {
+            let rotate = |v: &Vec3| {
+                Vec3::new(
+                    self.cos_theta * v.x + self.sin_theta * v.z,
+                    v.y,
+                    -self.sin_theta * v.x + self.cos_theta * v.z,
+                )
+            };
+
+            rec.point = rotate(&rec.point);
+            let normal = rotate(&rec.normal);
+            let geometric_normal = rotate(&rec.geometric_normal);
+            rec.dpdu = rotate(&rec.dpdu);
+            rec.dpdv = rotate(&rec.dpdv);
 
-            p.x = self.cos_theta * rec.point.x + self.sin_theta * rec.point.z;
-            p.z = -self.sin_theta * rec.point.x + self.cos_theta * rec.point.z;
-
-            normal.x = self.cos_theta * rec.normal.x + self.sin_theta * rec.normal.z;
-            normal.z = -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z;
-
-            rec.point = p;
             rec.set_face_normal(&rotated_r, &normal);
+            rec.geometric_normal = geometric_normal;
 
             return Some(rec);
         } else {
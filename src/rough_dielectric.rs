@@ -0,0 +1,112 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    microfacet::{self, Ggx},
+    ray::Ray,
+    sampler::Sampler,
+    utilities::Frame,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::any::Any;
+
+// Glass with a rough surface, like frosted or etched glass, following
+// "Microfacet Models for Refraction through Rough Surfaces" (Walter et al.
+// 2007). Unlike `Dielectric` it uses the exact Fresnel equations.
+pub struct RoughDielectric {
+    pub ir: f32, // Index of Refraction
+    pub roughness: f32,
+    distribution: Ggx,
+}
+
+impl RoughDielectric {
+    pub fn new(ir: f32, roughness: f32) -> Self {
+        let roughness = roughness.clamp(0.0, 1.0);
+        Self {
+            ir,
+            roughness,
+            distribution: Ggx::from_roughness(roughness),
+        }
+    }
+
+    // How much denser the side the ray goes into is than the one it comes from.
+    fn eta(&self, rec: &HitRecord) -> f32 {
+        if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::black();
+        }
+
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        Color::white() * microfacet::dielectric_eval(&self.distribution, &wo, &wi, self.eta(rec))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f32 {
+        if self.distribution.is_smooth() {
+            return 0.0;
+        }
+
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        microfacet::dielectric_pdf(&self.distribution, &wo, &wi, self.eta(rec))
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let eta = self.eta(rec);
+        if self.distribution.is_smooth() {
+            let n = Vec3::new(0.0, 0.0, 1.0);
+            let f = microfacet::fresnel_dielectric(wo.z, eta);
+            let wi = match microfacet::refract(&wo, &n, eta) {
+                Some(wi) if sampler.random_float() >= f => wi,
+                _ => microfacet::reflect(&wo, &n),
+            };
+
+            // Reflecting or refracting is picked with the probability it
+            // happens with, so nothing is lost either way.
+            return Some(BsdfSample {
+                direction: frame.to_world(&wi),
+                weight: Color::white(),
+                pdf: 1.0,
+                is_specular: true,
+            });
+        }
+
+        let wi = microfacet::dielectric_sample(&self.distribution, &wo, eta, sampler)?;
+        let g = self.distribution.g(&wo, &wi) / self.distribution.g1(&wo);
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: Color::white() * g,
+            pdf: microfacet::dielectric_pdf(&self.distribution, &wo, &wi, eta),
+            is_specular: false,
+        })
+    }
+
+    fn emitted(&self, _uv: &Vec2, _p: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,121 @@
+use crate::color::Color;
+use cliffy::{Vec3, Vector};
+use rand::prelude::*;
+
+// The source of every random number used while building and rendering a scene.
+// Nothing reads global or thread-local state, so the same seed always gives the
+// same image, and each pixel gets its own stream so the result doesn't depend on
+// the order pixels are rendered in or on the number of threads.
+pub struct Sampler {
+    rng: StdRng,
+}
+
+// SplitMix64 finalizer, used to derive well-separated seeds from a base seed.
+fn mix_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn for_pixel(seed: u64, x: u32, y: u32) -> Self {
+        let index = ((y as u64) << 32) | x as u64;
+        Self::new(mix_seed(seed, index))
+    }
+
+    #[inline]
+    pub fn random_float(&mut self) -> f32 {
+        self.rng.gen_range(0.0..1.0)
+    }
+
+    #[inline]
+    pub fn random_float_between(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.random_float()
+    }
+
+    #[inline]
+    pub fn random_int(&mut self, min: i32, max: i32) -> i32 {
+        self.rng.gen_range(min..=max)
+    }
+
+    #[inline]
+    pub fn random_vec3(&mut self) -> Vec3 {
+        Vec3::new(
+            self.random_float(),
+            self.random_float(),
+            self.random_float(),
+        )
+    }
+
+    #[inline]
+    pub fn random_vec3_between(&mut self, min: f32, max: f32) -> Vec3 {
+        Vec3::new(
+            self.random_float_between(min, max),
+            self.random_float_between(min, max),
+            self.random_float_between(min, max),
+        )
+    }
+
+    #[inline]
+    pub fn random_color(&mut self) -> Color {
+        Color::new(
+            self.random_float(),
+            self.random_float(),
+            self.random_float(),
+        )
+    }
+
+    #[inline]
+    pub fn random_color_between(&mut self, min: f32, max: f32) -> Color {
+        Color::new(
+            self.random_float_between(min, max),
+            self.random_float_between(min, max),
+            self.random_float_between(min, max),
+        )
+    }
+
+    #[inline]
+    pub fn random_in_sphere(&mut self) -> Vec3 {
+        loop {
+            let p = self.random_vec3_between(-1.0, 1.0);
+            if p.mag_sq() < 1.0 {
+                return p;
+            }
+        }
+    }
+
+    pub fn random_unit_vec3(&mut self) -> Vec3 {
+        self.random_in_sphere().normalized()
+    }
+
+    pub fn random_in_hemisphere(&mut self, normal: &Vec3) -> Vec3 {
+        let in_unit_sphere = self.random_in_sphere();
+
+        // If in the same hemisphere as the normal
+        if in_unit_sphere.dot(*normal) > 0.0 {
+            in_unit_sphere
+        } else {
+            -in_unit_sphere
+        }
+    }
+
+    pub fn random_in_disk(&mut self) -> Vec3 {
+        loop {
+            let p = Vec3::new(
+                self.random_float_between(-1.0, 1.0),
+                self.random_float_between(-1.0, 1.0),
+                0.0,
+            );
+            if p.mag_sq() < 1.0 {
+                return p;
+            }
+        }
+    }
+}
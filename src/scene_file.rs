@@ -0,0 +1,2197 @@
+use crate::{
+    animated_transform::{AnimatedTransform, Keyframe},
+    bump::{BumpMap, Bumped},
+    bvh_node::BVHNode,
+    coated::Coated,
+    color::Color,
+    conductor::Conductor,
+    constant_medium::ConstantMedium,
+    dielectric::{Dielectric, Dispersion},
+    diffuse_light::DiffuseLight,
+    geo_box::GeoBox,
+    gltf_scene::{self, GltfError},
+    hittable::Hittable,
+    hittable_list::HittableList,
+    instance::Instance,
+    isotropic::Isotropic,
+    lambertian::Lambertian,
+    linear_bvh::LinearBVH,
+    mat4::Mat4,
+    material::Material,
+    metal::Metal,
+    mix::Mix,
+    moving_sphere::MovingSphere,
+    obj::{self, ObjError},
+    perlin::{self, Perlin},
+    ply::{self, PlyError},
+    principled::Principled,
+    quaternion,
+    rect::{XYRect, XZRect, YZRect},
+    rotate::RotateY,
+    rough_dielectric::RoughDielectric,
+    sampler::Sampler,
+    scenes::Scene,
+    sphere::Sphere,
+    subsurface::Subsurface,
+    texture::Texture,
+    thin_film::ThinFilm,
+    transform::Transform,
+    translate::Translate,
+    triangle::{MeshVertices, Triangle, TriangleMesh},
+};
+use cliffy::{Vec2, Vec3, Vector};
+use image::io::Reader as ImageReader;
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        DeserializeOwned, MapAccess, SeqAccess, Unexpected, Visitor,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Scene files are JSON documents. Textures, materials and shapes can be declared
+// once under a name and then referenced by that name wherever one is expected,
+// which shares a single instance between all the users, or written inline.
+//
+// {
+//     "camera": { "look_from": [13, 2, 3], "look_at": [0, 0, 0], "vfov": 20 },
+//     "image": { "width": 400, "aspect_ratio": 1.7778, "samples_per_pixel": 100 },
+//     "background": [0.7, 0.8, 1.0],
+//     "textures": { "checker": { "type": "checker", "scale": 10, "even": [0.2, 0.3, 0.1], "odd": [0.9, 0.9, 0.9] } },
+//     "materials": { "ground": { "type": "lambertian", "albedo": "checker" } },
+//     "objects": [ { "type": "sphere", "center": [0, -10, 0], "radius": 10, "material": "ground" } ]
+// }
+//
+// See the files in `scenes/` for every supported type.
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Image(PathBuf, image::ImageError),
+    Obj(ObjError),
+    Gltf(GltfError),
+    Ply(PlyError),
+    UnknownReference {
+        kind: &'static str,
+        name: String,
+        context: String,
+    },
+    Cycle {
+        kind: &'static str,
+        name: String,
+    },
+    Invalid {
+        context: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            // serde_json reports the line and column of the problem itself.
+            Self::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Image(path, e) => write!(f, "failed to load image {}: {}", path.display(), e),
+            Self::Obj(e) => write!(f, "{}", e),
+            Self::Gltf(e) => write!(f, "{}", e),
+            Self::Ply(e) => write!(f, "{}", e),
+            Self::UnknownReference {
+                kind,
+                name,
+                context,
+            } => write!(f, "{}: unknown {} `{}`", context, kind, name),
+            Self::Cycle { kind, name } => {
+                write!(f, "{} `{}` is defined in terms of itself", kind, name)
+            }
+            Self::Invalid { context, message } => write!(f, "{}: {}", context, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    #[serde(default)]
+    image: ImageDesc,
+    #[serde(default)]
+    background: [f32; 3],
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    textures: BTreeMap<String, TextureDesc>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    materials: BTreeMap<String, MaterialDesc>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    shapes: BTreeMap<String, ObjectDesc>,
+    objects: Vec<ObjectRef>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_vup")]
+    vup: [f32; 3],
+    #[serde(default = "default_vfov")]
+    vfov: f32,
+    #[serde(default)]
+    aperture: f32,
+    #[serde(default = "default_focus_dist")]
+    focus_dist: f32,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ImageDesc {
+    #[serde(default = "default_width")]
+    width: u32,
+    #[serde(default = "default_aspect_ratio")]
+    aspect_ratio: f32,
+    #[serde(default = "default_samples_per_pixel")]
+    samples_per_pixel: u32,
+}
+
+impl Default for ImageDesc {
+    fn default() -> Self {
+        Self {
+            width: default_width(),
+            aspect_ratio: default_aspect_ratio(),
+            samples_per_pixel: default_samples_per_pixel(),
+        }
+    }
+}
+
+fn default_vup() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f32 {
+    40.0
+}
+
+fn default_focus_dist() -> f32 {
+    10.0
+}
+
+fn default_transform() -> [[f32; 4]; 4] {
+    Mat4::identity().m
+}
+
+fn default_width() -> u32 {
+    400
+}
+
+fn default_aspect_ratio() -> f32 {
+    16.0 / 9.0
+}
+
+fn default_samples_per_pixel() -> u32 {
+    100
+}
+
+// A texture can be given by name, as a plain [r, g, b] color, as a single number
+// for a gray or inline.
+enum TextureRef {
+    Name(String),
+    Color([f32; 3]),
+    Inline(Box<TextureDesc>),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid {
+        color: [f32; 3],
+    },
+    Checker {
+        scale: f32,
+        even: TextureRef,
+        odd: TextureRef,
+    },
+    // `texture` multiplied by `factor`, channel by channel.
+    Scaled {
+        factor: [f32; 3],
+        texture: TextureRef,
+    },
+    Noise {
+        scale: f32,
+        // The lattice of a particular noise, as written by `save`. Without it
+        // a fresh random one is generated.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        perlin: Option<PerlinDesc>,
+    },
+    Image {
+        // Relative paths are resolved against the scene file's directory.
+        path: PathBuf,
+    },
+    // The vertex colors of the mesh that was hit.
+    VertexColor,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PerlinDesc {
+    ran_vec: Vec<[f32; 3]>,
+    perm_x: Vec<u32>,
+    perm_y: Vec<u32>,
+    perm_z: Vec<u32>,
+}
+
+enum MaterialRef {
+    Name(String),
+    Inline(Box<MaterialDesc>),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: [f32; 3],
+        fuzz: f32,
+    },
+    // With a `dispersion`, `ir` is worked out from it.
+    Dielectric {
+        #[serde(default = "default_ir")]
+        ir: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dispersion: Option<DispersionDesc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        film: Option<FilmDesc>,
+    },
+    // A smooth or rough metal, either one of the `conductor::PRESETS` or given
+    // by its complex index of refraction.
+    Conductor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preset: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        eta: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        k: Option<[f32; 3]>,
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        film: Option<FilmDesc>,
+    },
+    RoughDielectric {
+        ir: f32,
+        roughness: f32,
+    },
+    // Another material under a smooth or rough dielectric coat, optionally one
+    // that absorbs `absorption` per unit of `thickness`.
+    Coated {
+        base: MaterialRef,
+        #[serde(default = "default_ir")]
+        ir: f32,
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default)]
+        thickness: f32,
+        #[serde(default)]
+        absorption: [f32; 3],
+    },
+    // A closed surface around a medium light scatters through, `albedo` being
+    // the color it ends up with and `mean_free_path` how far it goes between
+    // scattering events.
+    Subsurface {
+        albedo: TextureRef,
+        mean_free_path: [f32; 3],
+        #[serde(default = "default_ir")]
+        ir: f32,
+        #[serde(default)]
+        anisotropy: f32,
+    },
+    // `first` where `factor` is 0, `second` where it's 1, and a blend of the
+    // two in between.
+    Mix {
+        first: MaterialRef,
+        second: MaterialRef,
+        factor: TextureRef,
+    },
+    // `base` with its shading normals read from a tangent-space normal map,
+    // leaning `strength` times as far as the map has them.
+    NormalMap {
+        base: MaterialRef,
+        map: TextureRef,
+        #[serde(default = "default_strength")]
+        strength: f32,
+    },
+    // `base` shaded as if raised by `height` times `scale`, in scene units.
+    Bump {
+        base: MaterialRef,
+        height: TextureRef,
+        scale: f32,
+    },
+    DiffuseLight {
+        emit: TextureRef,
+    },
+    Isotropic {
+        albedo: TextureRef,
+    },
+    // Every parameter but `ir` is optional, and can be a texture, a color or,
+    // for those that are a single number, just that.
+    Principled {
+        base_color: TextureRef,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metallic: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        roughness: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        specular: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        specular_tint: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sheen: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sheen_tint: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clearcoat_gloss: Option<TextureRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transmission: Option<TextureRef>,
+        #[serde(default = "default_ir")]
+        ir: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        emission: Option<TextureRef>,
+    },
+}
+
+fn default_ir() -> f32 {
+    1.5
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+// Coefficients for wavelengths in micrometers, as glass makers publish them.
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DispersionDesc {
+    Cauchy { a: f32, b: f32 },
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+// A thin film over a dielectric or conductor, `thickness` in micrometers.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct FilmDesc {
+    thickness: TextureRef,
+    #[serde(default = "default_film_ir")]
+    ir: f32,
+}
+
+// That of water, or soap.
+fn default_film_ir() -> f32 {
+    1.33
+}
+
+enum ObjectRef {
+    Name(String),
+    Inline(Box<ObjectDesc>),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: MaterialRef,
+    },
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: MaterialRef,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: MaterialRef,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: MaterialRef,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: MaterialRef,
+    },
+    Box {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: MaterialRef,
+    },
+    Translate {
+        offset: [f32; 3],
+        object: ObjectRef,
+    },
+    RotateY {
+        angle: f32,
+        object: ObjectRef,
+    },
+    ConstantMedium {
+        boundary: ObjectRef,
+        density: f32,
+        albedo: TextureRef,
+    },
+    Bvh {
+        objects: Vec<ObjectRef>,
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<[[f32; 3]; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<[[f32; 2]; 3]>,
+        material: MaterialRef,
+    },
+    // An indexed mesh, `normals` and `uvs` are optional but when given need one
+    // entry per position.
+    Mesh {
+        positions: Vec<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        normals: Vec<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        uvs: Vec<[f32; 2]>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        colors: Vec<[f32; 3]>,
+        indices: Vec<[usize; 3]>,
+        material: MaterialRef,
+    },
+    // A Wavefront OBJ file, with its MTL materials unless `material` is given.
+    // Saving the scene writes the loaded triangles out as meshes.
+    Obj {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<MaterialRef>,
+    },
+    // A Stanford PLY mesh. Without a material it is a Lambertian, textured with
+    // the vertex colors if it has any.
+    Ply {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<MaterialRef>,
+    },
+    // The meshes and lights of a glTF file, its cameras are ignored. Saving the
+    // scene writes them out as meshes and spheres.
+    Gltf {
+        path: PathBuf,
+    },
+    // Any affine transform of another object: scaled, then rotated around X, Y
+    // and Z by the angles in degrees, then translated, then put through
+    // `matrix`, given row by row. Each step is optional.
+    Transform {
+        object: ObjectRef,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scale: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rotate: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        translate: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        matrix: Option<[[f32; 4]; 4]>,
+    },
+    // An object moving through `keyframes` as the shutter goes from time 0 to 1,
+    // holding still before the first and after the last.
+    AnimatedTransform {
+        object: ObjectRef,
+        keyframes: Vec<KeyframeDesc>,
+    },
+    // Another object, usually a named shape shared by many instances, under a
+    // 4x4 affine transform given row by row, optionally with its own material.
+    Instance {
+        object: ObjectRef,
+        #[serde(default = "default_transform")]
+        transform: [[f32; 4]; 4],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<MaterialRef>,
+    },
+}
+
+// Scales, then rotates, either around X, Y and Z by the angles in degrees or by
+// a quaternion [x, y, z, w], then translates. Every step is optional.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDesc {
+    time: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotate: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translate: Option<[f32; 3]>,
+}
+
+// References are deserialized by hand rather than with `#[serde(untagged)]`, so
+// that a mistake inside an inline definition is reported as is, with its line
+// and column, instead of as "data did not match any variant".
+struct RefVisitor<T>(&'static str, PhantomData<T>);
+
+trait FromRef: Sized {
+    type Desc: DeserializeOwned;
+
+    fn name(name: String) -> Self;
+    fn inline(desc: Self::Desc) -> Self;
+    fn color(_color: [f32; 3]) -> Option<Self> {
+        None
+    }
+
+    fn number(_number: f32) -> Option<Self> {
+        None
+    }
+}
+
+impl<'de, T: FromRef> Visitor<'de> for RefVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the name of a {} or an inline definition", self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        Ok(T::name(v.to_string()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::Desc::deserialize(MapAccessDeserializer::new(map)).map(T::inline)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<T, A::Error> {
+        let c = <[f32; 3]>::deserialize(SeqAccessDeserializer::new(seq))?;
+        T::color(c).ok_or_else(|| de::Error::invalid_type(Unexpected::Seq, &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<T, E> {
+        T::number(v as f32).ok_or_else(|| de::Error::invalid_type(Unexpected::Float(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        T::number(v as f32).ok_or_else(|| de::Error::invalid_type(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        T::number(v as f32).ok_or_else(|| de::Error::invalid_type(Unexpected::Signed(v), &self))
+    }
+}
+
+impl FromRef for TextureRef {
+    type Desc = TextureDesc;
+
+    fn name(name: String) -> Self {
+        Self::Name(name)
+    }
+
+    fn inline(desc: TextureDesc) -> Self {
+        Self::Inline(Box::new(desc))
+    }
+
+    fn color(color: [f32; 3]) -> Option<Self> {
+        Some(Self::Color(color))
+    }
+
+    // A gray, for parameters that are a single number.
+    fn number(number: f32) -> Option<Self> {
+        Some(Self::Color([number; 3]))
+    }
+}
+
+impl FromRef for MaterialRef {
+    type Desc = MaterialDesc;
+
+    fn name(name: String) -> Self {
+        Self::Name(name)
+    }
+
+    fn inline(desc: MaterialDesc) -> Self {
+        Self::Inline(Box::new(desc))
+    }
+}
+
+impl FromRef for ObjectRef {
+    type Desc = ObjectDesc;
+
+    fn name(name: String) -> Self {
+        Self::Name(name)
+    }
+
+    fn inline(desc: ObjectDesc) -> Self {
+        Self::Inline(Box::new(desc))
+    }
+}
+
+impl<'de> Deserialize<'de> for TextureRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RefVisitor("texture", PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for MaterialRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RefVisitor("material", PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RefVisitor("shape", PhantomData))
+    }
+}
+
+impl Serialize for TextureRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Name(name) => name.serialize(serializer),
+            Self::Color(c) => c.serialize(serializer),
+            Self::Inline(desc) => desc.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for MaterialRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Name(name) => name.serialize(serializer),
+            Self::Inline(desc) => desc.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for ObjectRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Name(name) => name.serialize(serializer),
+            Self::Inline(desc) => desc.serialize(serializer),
+        }
+    }
+}
+
+fn vec3(v: &[f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(c: &[f32; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+fn to_array(v: &Vec3) -> [f32; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn vec2(v: &[f32; 2]) -> Vec2 {
+    Vec2::new(v[0], v[1])
+}
+
+fn to_array2(v: &Vec2) -> [f32; 2] {
+    [v.x, v.y]
+}
+
+fn color_to_array(c: &Color) -> [f32; 3] {
+    [c.r, c.g, c.b]
+}
+
+struct Loader<'a> {
+    desc: &'a SceneDesc,
+    base_dir: PathBuf,
+    sampler: &'a mut Sampler,
+    textures: HashMap<String, Arc<Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    shapes: HashMap<String, Arc<Hittable>>,
+    // Names currently being built, to catch definitions that refer to themselves.
+    pending: Vec<(&'static str, String)>,
+}
+
+impl<'a> Loader<'a> {
+    fn begin(&mut self, kind: &'static str, name: &str) -> Result<(), SceneError> {
+        if self.pending.iter().any(|(k, n)| *k == kind && n == name) {
+            return Err(SceneError::Cycle {
+                kind,
+                name: name.to_string(),
+            });
+        }
+        self.pending.push((kind, name.to_string()));
+
+        Ok(())
+    }
+
+    fn texture(&mut self, r: &TextureRef, context: &str) -> Result<Arc<Texture>, SceneError> {
+        match r {
+            TextureRef::Color(c) => Ok(Arc::new(Texture::SolidColor(color(c)))),
+            TextureRef::Inline(desc) => self.build_texture(desc, context),
+            TextureRef::Name(name) => {
+                if let Some(texture) = self.textures.get(name) {
+                    return Ok(texture.clone());
+                }
+
+                let desc =
+                    self.desc
+                        .textures
+                        .get(name)
+                        .ok_or_else(|| SceneError::UnknownReference {
+                            kind: "texture",
+                            name: name.clone(),
+                            context: context.to_string(),
+                        })?;
+
+                self.begin("texture", name)?;
+                let texture = self.build_texture(desc, &format!("textures.{}", name))?;
+                self.pending.pop();
+                self.textures.insert(name.clone(), texture.clone());
+
+                Ok(texture)
+            }
+        }
+    }
+
+    fn build_texture(
+        &mut self,
+        desc: &TextureDesc,
+        context: &str,
+    ) -> Result<Arc<Texture>, SceneError> {
+        let texture = match desc {
+            TextureDesc::Solid { color: c } => Texture::SolidColor(color(c)),
+            TextureDesc::Checker { scale, even, odd } => Texture::Checker(
+                *scale,
+                self.texture(even, context)?,
+                self.texture(odd, context)?,
+            ),
+            TextureDesc::Scaled { factor, texture } => {
+                Texture::Scaled(color(factor), self.texture(texture, context)?)
+            }
+            TextureDesc::Noise { scale, perlin } => {
+                let perlin = match perlin {
+                    Some(perlin) => Self::build_perlin(perlin, context)?,
+                    None => Perlin::new(self.sampler),
+                };
+                Texture::Noise(*scale, perlin)
+            }
+            TextureDesc::Image { path } => {
+                let path = self.base_dir.join(path);
+                let image = ImageReader::open(&path)
+                    .map_err(|e| SceneError::Io(path.clone(), e))?
+                    .decode()
+                    .map_err(|e| SceneError::Image(path.clone(), e))?;
+                Texture::Image(image, Some(path))
+            }
+            TextureDesc::VertexColor => Texture::VertexColor,
+        };
+
+        Ok(Arc::new(texture))
+    }
+
+    fn build_perlin(desc: &PerlinDesc, context: &str) -> Result<Perlin, SceneError> {
+        let perms = [&desc.perm_x, &desc.perm_y, &desc.perm_z];
+        let valid = desc.ran_vec.len() == perlin::POINT_COUNT
+            && perms.iter().all(|perm| {
+                perm.len() == perlin::POINT_COUNT
+                    && perm.iter().all(|&i| (i as usize) < perlin::POINT_COUNT)
+            });
+
+        if !valid {
+            return Err(SceneError::Invalid {
+                context: context.to_string(),
+                message: format!(
+                    "perlin tables must hold {} entries each, indexing into ran_vec",
+                    perlin::POINT_COUNT
+                ),
+            });
+        }
+
+        Ok(Perlin {
+            ran_vec: desc.ran_vec.iter().map(vec3).collect(),
+            perm_x: desc.perm_x.clone(),
+            perm_y: desc.perm_y.clone(),
+            perm_z: desc.perm_z.clone(),
+        })
+    }
+
+    fn material(
+        &mut self,
+        r: &MaterialRef,
+        context: &str,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        match r {
+            MaterialRef::Inline(desc) => self.build_material(desc, context),
+            MaterialRef::Name(name) => {
+                if let Some(material) = self.materials.get(name) {
+                    return Ok(material.clone());
+                }
+
+                let desc =
+                    self.desc
+                        .materials
+                        .get(name)
+                        .ok_or_else(|| SceneError::UnknownReference {
+                            kind: "material",
+                            name: name.clone(),
+                            context: context.to_string(),
+                        })?;
+
+                self.begin("material", name)?;
+                let material = self.build_material(desc, &format!("materials.{}", name))?;
+                self.pending.pop();
+                self.materials.insert(name.clone(), material.clone());
+
+                Ok(material)
+            }
+        }
+    }
+
+    fn build_material(
+        &mut self,
+        desc: &MaterialDesc,
+        context: &str,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        let material: Arc<dyn Material> = match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::new(self.texture(albedo, context)?))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::new(color(albedo), *fuzz)),
+            MaterialDesc::Dielectric {
+                ir,
+                dispersion,
+                film,
+            } => {
+                let mut m = match dispersion {
+                    Some(DispersionDesc::Cauchy { a, b }) => {
+                        Dielectric::with_dispersion(Dispersion::Cauchy { a: *a, b: *b })
+                    }
+                    Some(DispersionDesc::Sellmeier { b, c }) => {
+                        Dielectric::with_dispersion(Dispersion::Sellmeier { b: *b, c: *c })
+                    }
+                    None => Dielectric::new(*ir),
+                };
+                m.film = self.film(film, context)?;
+                Arc::new(m)
+            }
+            MaterialDesc::Conductor {
+                preset,
+                eta,
+                k,
+                roughness,
+                film,
+            } => {
+                let invalid = |message: String| SceneError::Invalid {
+                    context: context.to_string(),
+                    message,
+                };
+                let mut conductor = match (preset, eta, k) {
+                    (Some(name), None, None) => Conductor::preset(name, *roughness)
+                        .ok_or_else(|| invalid(format!("unknown metal `{}`", name)))?,
+                    (None, Some(eta), Some(k)) => Conductor::new(color(eta), color(k), *roughness),
+                    _ => {
+                        return Err(invalid(
+                            "give either a preset or both eta and k".to_string(),
+                        ))
+                    }
+                };
+                conductor.film = self.film(film, context)?;
+                Arc::new(conductor)
+            }
+            MaterialDesc::RoughDielectric { ir, roughness } => {
+                Arc::new(RoughDielectric::new(*ir, *roughness))
+            }
+            MaterialDesc::Coated {
+                base,
+                ir,
+                roughness,
+                thickness,
+                absorption,
+            } => {
+                let mut m = Coated::new(self.material(base, context)?, *ir, *roughness);
+                m.thickness = *thickness;
+                m.absorption = color(absorption);
+                Arc::new(m)
+            }
+            MaterialDesc::Subsurface {
+                albedo,
+                mean_free_path,
+                ir,
+                anisotropy,
+            } => {
+                let mut m = Subsurface::new(self.texture(albedo, context)?, color(mean_free_path));
+                m.ir = *ir;
+                m.anisotropy = *anisotropy;
+                Arc::new(m)
+            }
+            MaterialDesc::Mix {
+                first,
+                second,
+                factor,
+            } => Arc::new(Mix::new(
+                self.material(first, context)?,
+                self.material(second, context)?,
+                self.texture(factor, context)?,
+            )),
+            MaterialDesc::NormalMap {
+                base,
+                map,
+                strength,
+            } => Arc::new(Bumped::new(
+                self.material(base, context)?,
+                BumpMap::Normal(self.texture(map, context)?, *strength),
+            )),
+            MaterialDesc::Bump {
+                base,
+                height,
+                scale,
+            } => Arc::new(Bumped::new(
+                self.material(base, context)?,
+                BumpMap::Height(self.texture(height, context)?, *scale),
+            )),
+            MaterialDesc::DiffuseLight { emit } => {
+                Arc::new(DiffuseLight::new(self.texture(emit, context)?))
+            }
+            MaterialDesc::Isotropic { albedo } => {
+                Arc::new(Isotropic::new(self.texture(albedo, context)?))
+            }
+            MaterialDesc::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                sheen_tint,
+                clearcoat,
+                clearcoat_gloss,
+                transmission,
+                ir,
+                emission,
+            } => {
+                let mut m = Principled::new(self.texture(base_color, context)?);
+                let fields = [
+                    (metallic, &mut m.metallic),
+                    (roughness, &mut m.roughness),
+                    (specular, &mut m.specular),
+                    (specular_tint, &mut m.specular_tint),
+                    (sheen, &mut m.sheen),
+                    (sheen_tint, &mut m.sheen_tint),
+                    (clearcoat, &mut m.clearcoat),
+                    (clearcoat_gloss, &mut m.clearcoat_gloss),
+                    (transmission, &mut m.transmission),
+                    (emission, &mut m.emission),
+                ];
+                for (r, field) in fields {
+                    if let Some(r) = r {
+                        *field = self.texture(r, context)?;
+                    }
+                }
+                m.ir = *ir;
+                Arc::new(m)
+            }
+        };
+
+        Ok(material)
+    }
+
+    fn film(
+        &mut self,
+        desc: &Option<FilmDesc>,
+        context: &str,
+    ) -> Result<Option<ThinFilm>, SceneError> {
+        match desc {
+            Some(desc) => Ok(Some(ThinFilm::new(
+                self.texture(&desc.thickness, context)?,
+                desc.ir,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    fn object(&mut self, r: &ObjectRef, context: &str) -> Result<Arc<Hittable>, SceneError> {
+        match r {
+            ObjectRef::Inline(desc) => self.build_object(desc, context),
+            ObjectRef::Name(name) => {
+                if let Some(object) = self.shapes.get(name) {
+                    return Ok(object.clone());
+                }
+
+                let desc =
+                    self.desc
+                        .shapes
+                        .get(name)
+                        .ok_or_else(|| SceneError::UnknownReference {
+                            kind: "shape",
+                            name: name.clone(),
+                            context: context.to_string(),
+                        })?;
+
+                self.begin("shape", name)?;
+                let object = self.build_object(desc, &format!("shapes.{}", name))?;
+                self.pending.pop();
+                self.shapes.insert(name.clone(), object.clone());
+
+                Ok(object)
+            }
+        }
+    }
+
+    fn build_object(
+        &mut self,
+        desc: &ObjectDesc,
+        context: &str,
+    ) -> Result<Arc<Hittable>, SceneError> {
+        let object = match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Hittable::Sphere(Sphere::new(
+                vec3(center),
+                *radius,
+                self.material(material, context)?,
+            )),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => {
+                if time0 == time1 {
+                    return Err(SceneError::Invalid {
+                        context: context.to_string(),
+                        message: "time0 and time1 must differ".to_string(),
+                    });
+                }
+
+                Hittable::MovingSphere(MovingSphere::new(
+                    vec3(center0),
+                    vec3(center1),
+                    *time0,
+                    *time1,
+                    *radius,
+                    self.material(material, context)?,
+                ))
+            }
+            ObjectDesc::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Hittable::XYRect(XYRect::new(
+                self.material(material, context)?,
+                *x0,
+                *x1,
+                *y0,
+                *y1,
+                *k,
+            )),
+            ObjectDesc::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Hittable::XZRect(XZRect::new(
+                self.material(material, context)?,
+                *x0,
+                *x1,
+                *z0,
+                *z1,
+                *k,
+            )),
+            ObjectDesc::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Hittable::YZRect(YZRect::new(
+                self.material(material, context)?,
+                *y0,
+                *y1,
+                *z0,
+                *z1,
+                *k,
+            )),
+            ObjectDesc::Box { min, max, material } => Hittable::Box(GeoBox::new(
+                &vec3(min),
+                &vec3(max),
+                self.material(material, context)?,
+            )),
+            ObjectDesc::Translate { offset, object } => {
+                Hittable::Translate(Translate::new(self.object(object, context)?, vec3(offset)))
+            }
+            ObjectDesc::RotateY { angle, object } => {
+                Hittable::RotateY(RotateY::new(self.object(object, context)?, *angle))
+            }
+            ObjectDesc::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => Hittable::ConstantMedium(ConstantMedium::new(
+                self.object(boundary, context)?,
+                *density,
+                self.texture(albedo, context)?,
+            )),
+            ObjectDesc::Bvh { objects } => {
+                if objects.is_empty() {
+                    return Err(SceneError::Invalid {
+                        context: context.to_string(),
+                        message: "a bvh needs at least one object".to_string(),
+                    });
+                }
+
+                let mut list = HittableList::empty();
+                for (i, object) in objects.iter().enumerate() {
+                    list.add(self.object(object, &format!("{}.objects[{}]", context, i))?);
+                }
+                Hittable::Bvh(LinearBVH::new(&mut list, 0.0, 1.0))
+            }
+            ObjectDesc::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material,
+            } => {
+                let vertices = MeshVertices::new(
+                    vertices.iter().map(vec3).collect(),
+                    normals.iter().flatten().map(vec3).collect(),
+                    uvs.iter().flatten().map(vec2).collect(),
+                );
+                Hittable::Triangle(Triangle::new(
+                    Arc::new(vertices),
+                    [0, 1, 2],
+                    self.material(material, context)?,
+                ))
+            }
+            ObjectDesc::Mesh {
+                positions,
+                normals,
+                uvs,
+                colors,
+                indices,
+                material,
+            } => {
+                let invalid = |message: String| SceneError::Invalid {
+                    context: context.to_string(),
+                    message,
+                };
+                if indices.is_empty() {
+                    return Err(invalid("a mesh needs at least one triangle".to_string()));
+                }
+                if !normals.is_empty() && normals.len() != positions.len() {
+                    return Err(invalid(format!(
+                        "{} normals given for {} positions",
+                        normals.len(),
+                        positions.len()
+                    )));
+                }
+                if !uvs.is_empty() && uvs.len() != positions.len() {
+                    return Err(invalid(format!(
+                        "{} uvs given for {} positions",
+                        uvs.len(),
+                        positions.len()
+                    )));
+                }
+                if !colors.is_empty() && colors.len() != positions.len() {
+                    return Err(invalid(format!(
+                        "{} colors given for {} positions",
+                        colors.len(),
+                        positions.len()
+                    )));
+                }
+                if let Some(i) = indices.iter().flatten().find(|&&i| i >= positions.len()) {
+                    return Err(invalid(format!(
+                        "index {} is out of range for {} positions",
+                        i,
+                        positions.len()
+                    )));
+                }
+
+                let vertices = MeshVertices::with_colors(
+                    positions.iter().map(vec3).collect(),
+                    normals.iter().map(vec3).collect(),
+                    uvs.iter().map(vec2).collect(),
+                    colors.iter().map(color).collect(),
+                );
+                Hittable::Mesh(TriangleMesh::new(
+                    Arc::new(vertices),
+                    indices.clone(),
+                    self.material(material, context)?,
+                ))
+            }
+            ObjectDesc::Obj { path, material } => {
+                let material = match material {
+                    Some(material) => Some(self.material(material, context)?),
+                    None => None,
+                };
+                obj::load(&self.base_dir.join(path), material).map_err(SceneError::Obj)?
+            }
+            ObjectDesc::Ply { path, material } => {
+                let material = match material {
+                    Some(material) => Some(self.material(material, context)?),
+                    None => None,
+                };
+                let mesh =
+                    ply::load(&self.base_dir.join(path), material).map_err(SceneError::Ply)?;
+                Hittable::Mesh(mesh)
+            }
+            ObjectDesc::Gltf { path } => {
+                let mut list = gltf_scene::load(&self.base_dir.join(path))
+                    .map_err(SceneError::Gltf)?
+                    .objects;
+                if list.objects.is_empty() {
+                    return Err(SceneError::Invalid {
+                        context: context.to_string(),
+                        message: "the glTF scene has no meshes or lights".to_string(),
+                    });
+                }
+                Hittable::Bvh(LinearBVH::new(&mut list, 0.0, 1.0))
+            }
+            ObjectDesc::Transform {
+                object,
+                scale,
+                rotate,
+                translate,
+                matrix,
+            } => {
+                let mut transform = Mat4::identity();
+                if let Some(scale) = scale {
+                    transform = Mat4::scaling(&vec3(scale)) * transform;
+                }
+                if let Some(rotate) = rotate {
+                    transform = Mat4::rotation_euler(&vec3(rotate)) * transform;
+                }
+                if let Some(translate) = translate {
+                    transform = Mat4::translation(&vec3(translate)) * transform;
+                }
+                if let Some(matrix) = matrix {
+                    transform = Mat4 { m: *matrix } * transform;
+                }
+                check_transform(&transform, context)?;
+
+                Hittable::Transform(Transform::new(
+                    self.object(object, &format!("{}.object", context))?,
+                    transform,
+                ))
+            }
+            ObjectDesc::AnimatedTransform { object, keyframes } => {
+                if keyframes.is_empty() {
+                    return Err(SceneError::Invalid {
+                        context: context.to_string(),
+                        message: "an animation needs at least one keyframe".to_string(),
+                    });
+                }
+
+                let keyframes = keyframes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, k)| keyframe(k, &format!("{}.keyframes[{}]", context, i)))
+                    .collect::<Result<_, _>>()?;
+                Hittable::AnimatedTransform(AnimatedTransform::new(
+                    self.object(object, &format!("{}.object", context))?,
+                    keyframes,
+                ))
+            }
+            ObjectDesc::Instance {
+                object,
+                transform,
+                material,
+            } => {
+                let transform = Mat4 { m: *transform };
+                check_transform(&transform, context)?;
+
+                let material = match material {
+                    Some(material) => Some(self.material(material, context)?),
+                    None => None,
+                };
+                Hittable::Instance(Instance::new(
+                    self.object(object, &format!("{}.object", context))?,
+                    transform,
+                    material,
+                ))
+            }
+        };
+
+        Ok(Arc::new(object))
+    }
+}
+
+// Loads a scene file, or a glTF file (`.gltf` or `.glb`) as a whole scene.
+pub fn load(path: &Path, sampler: &mut Sampler) -> Result<Scene, SceneError> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb") {
+        return gltf_scene::load_scene(path).map_err(SceneError::Gltf);
+    }
+
+    let text = fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+    let desc: SceneDesc =
+        serde_json::from_str(&text).map_err(|e| SceneError::Parse(path.to_path_buf(), e))?;
+    if desc.image.samples_per_pixel == 0 {
+        return Err(SceneError::Invalid {
+            context: "image.samples_per_pixel".to_string(),
+            message: "at least one sample per pixel is needed".to_string(),
+        });
+    }
+
+    let mut loader = Loader {
+        desc: &desc,
+        base_dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        sampler,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        shapes: HashMap::new(),
+        pending: Vec::new(),
+    };
+
+    let mut world = HittableList::empty();
+    for (i, object) in desc.objects.iter().enumerate() {
+        world.add(loader.object(object, &format!("objects[{}]", i))?);
+    }
+
+    Ok(Scene {
+        world,
+        background: color(&desc.background),
+        look_from: vec3(&desc.camera.look_from),
+        look_at: vec3(&desc.camera.look_at),
+        vup: vec3(&desc.camera.vup),
+        vfov: desc.camera.vfov,
+        aperture: desc.camera.aperture,
+        focus_dist: desc.camera.focus_dist,
+        aspect_ratio: desc.image.aspect_ratio,
+        image_width: desc.image.width,
+        samples_per_pixel: desc.image.samples_per_pixel,
+    })
+}
+
+// Walks an in-memory scene and turns it back into a scene description. Every
+// texture, material or shape that is shared by more than one user is written
+// once under a generated name and referenced by it, so loading the file again
+// recreates the same sharing.
+struct Exporter {
+    dir: PathBuf,
+    stem: String,
+    // How many times each `Arc` is referenced, keyed by its address.
+    uses: HashMap<usize, usize>,
+    names: HashMap<usize, String>,
+    textures: BTreeMap<String, TextureDesc>,
+    materials: BTreeMap<String, MaterialDesc>,
+    shapes: BTreeMap<String, ObjectDesc>,
+    // Images saved next to the scene so far, which numbers their files.
+    images_written: usize,
+}
+
+fn address<T: ?Sized>(arc: &Arc<T>) -> usize {
+    Arc::as_ptr(arc) as *const () as usize
+}
+
+// Transforms have to be affine and invertible.
+fn check_transform(transform: &Mat4, context: &str) -> Result<(), SceneError> {
+    let message = if transform.m[3] != [0.0, 0.0, 0.0, 1.0] {
+        "the last row of the transform has to be [0, 0, 0, 1]"
+    } else if transform.inverse().is_none() {
+        "the transform can't be inverted"
+    } else {
+        return Ok(());
+    };
+
+    Err(SceneError::Invalid {
+        context: context.to_string(),
+        message: message.to_string(),
+    })
+}
+
+fn keyframe(desc: &KeyframeDesc, context: &str) -> Result<Keyframe, SceneError> {
+    let invalid = |message: &str| SceneError::Invalid {
+        context: context.to_string(),
+        message: message.to_string(),
+    };
+
+    let scale = desc.scale.map_or(Vec3::uni(1.0), |s| vec3(&s));
+    if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+        return Err(invalid("the scale can't be zero"));
+    }
+
+    let rotation = match (desc.rotate, desc.rotation) {
+        (Some(_), Some(_)) => return Err(invalid("give either rotate or rotation, not both")),
+        (Some(rotate), None) => quaternion::from_euler(&vec3(&rotate)),
+        (None, Some(rotation)) => {
+            if rotation == [0.0; 4] {
+                return Err(invalid("the rotation quaternion can't be zero"));
+            }
+            quaternion::normalized(rotation)
+        }
+        (None, None) => quaternion::identity(),
+    };
+
+    Ok(Keyframe {
+        time: desc.time,
+        translation: desc.translate.map_or(Vec3::zero(), |t| vec3(&t)),
+        rotation,
+        scale,
+    })
+}
+
+// Collects the objects a BVH was built over. A lone object is referenced from
+// both sides of its node, so each is only kept once.
+fn bvh_leaves(node: &BVHNode) -> Vec<Arc<Hittable>> {
+    fn walk(node: &BVHNode, seen: &mut HashSet<usize>, leaves: &mut Vec<Arc<Hittable>>) {
+        for child in [&node.left, &node.right].iter() {
+            match &***child {
+                Hittable::Node(n) => walk(n, seen, leaves),
+                Hittable::List(list) => leaves.extend(list.objects.iter().cloned()),
+                _ => {
+                    if seen.insert(address(child)) {
+                        leaves.push((*child).clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut leaves = Vec::new();
+    walk(node, &mut HashSet::new(), &mut leaves);
+    leaves
+}
+
+// Expresses `path` relative to `dir` where both exist, so that exported scenes
+// keep working when moved together with their images.
+fn relative_path(path: &Path, dir: &Path) -> PathBuf {
+    let (path, dir) = match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => (path, dir),
+        _ => return path.to_path_buf(),
+    };
+
+    let common = path
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in path.components().skip(common) {
+        relative.push(component);
+    }
+
+    relative
+}
+
+impl Exporter {
+    // Returns true the first time `address` is seen.
+    fn count(&mut self, address: usize) -> bool {
+        let uses = self.uses.entry(address).or_insert(0);
+        *uses += 1;
+        *uses == 1
+    }
+
+    fn count_texture(&mut self, texture: &Arc<Texture>) {
+        if self.count(address(texture)) {
+            match &**texture {
+                Texture::Checker(_, even, odd) => {
+                    self.count_texture(even);
+                    self.count_texture(odd);
+                }
+                Texture::Scaled(_, texture) => self.count_texture(texture),
+                _ => {}
+            }
+        }
+    }
+
+    fn count_material(&mut self, material: &Arc<dyn Material>) {
+        if !self.count(address(material)) {
+            return;
+        }
+
+        let any = material.as_any();
+        if let Some(m) = any.downcast_ref::<Lambertian>() {
+            self.count_texture(&m.albedo);
+        } else if let Some(m) = any.downcast_ref::<DiffuseLight>() {
+            self.count_texture(&m.emit);
+        } else if let Some(m) = any.downcast_ref::<Isotropic>() {
+            self.count_texture(&m.albedo);
+        } else if let Some(m) = any.downcast_ref::<Principled>() {
+            for texture in m.textures() {
+                self.count_texture(texture);
+            }
+        } else if let Some(m) = any.downcast_ref::<Coated>() {
+            self.count_material(&m.base);
+        } else if let Some(m) = any.downcast_ref::<Subsurface>() {
+            self.count_texture(&m.albedo);
+        } else if let Some(m) = any.downcast_ref::<Mix>() {
+            self.count_material(&m.first);
+            self.count_material(&m.second);
+            self.count_texture(&m.factor);
+        } else if let Some(m) = any.downcast_ref::<Bumped>() {
+            self.count_material(&m.base);
+            match &m.map {
+                BumpMap::Normal(texture, _) | BumpMap::Height(texture, _) => {
+                    self.count_texture(texture)
+                }
+            }
+        } else if let Some(m) = any.downcast_ref::<Dielectric>() {
+            if let Some(film) = &m.film {
+                self.count_texture(&film.thickness);
+            }
+        } else if let Some(m) = any.downcast_ref::<Conductor>() {
+            if let Some(film) = &m.film {
+                self.count_texture(&film.thickness);
+            }
+        }
+    }
+
+    fn count_object(&mut self, object: &Arc<Hittable>) {
+        if !self.count(address(object)) {
+            return;
+        }
+
+        match &**object {
+            Hittable::Sphere(s) => self.count_material(&s.material),
+            Hittable::MovingSphere(s) => self.count_material(&s.material),
+            Hittable::Node(n) => {
+                for leaf in &bvh_leaves(n) {
+                    self.count_object(leaf);
+                }
+            }
+            Hittable::Bvh(bvh) => {
+                for object in &bvh.objects {
+                    self.count_object(object);
+                }
+            }
+            Hittable::List(list) => {
+                for object in &list.objects {
+                    self.count_object(object);
+                }
+            }
+            Hittable::XYRect(r) => self.count_material(&r.mp),
+            Hittable::XZRect(r) => self.count_material(&r.mp),
+            Hittable::YZRect(r) => self.count_material(&r.mp),
+            Hittable::Box(b) => self.count_material(&b.material),
+            Hittable::Translate(t) => self.count_object(&t.ptr),
+            Hittable::RotateY(r) => self.count_object(&r.ptr),
+            Hittable::ConstantMedium(m) => {
+                self.count_object(&m.boundary);
+                self.count_material(&m.phase_function);
+            }
+            Hittable::Triangle(t) => self.count_material(&t.material),
+            Hittable::Mesh(m) => self.count_material(&m.material),
+            Hittable::Transform(t) => self.count_object(&t.ptr),
+            Hittable::AnimatedTransform(t) => self.count_object(&t.ptr),
+            Hittable::Instance(inst) => {
+                self.count_object(&inst.object);
+                if let Some(material) = &inst.material {
+                    self.count_material(material);
+                }
+            }
+        }
+    }
+
+    fn shared_name(&mut self, address: usize, prefix: &str, count: usize) -> Option<String> {
+        if self.uses[&address] < 2 {
+            return None;
+        }
+
+        let name = format!("{}{}", prefix, count);
+        self.names.insert(address, name.clone());
+        Some(name)
+    }
+
+    fn texture(&mut self, texture: &Arc<Texture>) -> Result<TextureRef, SceneError> {
+        let key = address(texture);
+        if let Some(name) = self.names.get(&key) {
+            return Ok(TextureRef::Name(name.clone()));
+        }
+
+        let desc = match &**texture {
+            Texture::SolidColor(c) => {
+                if self.uses[&key] < 2 {
+                    return Ok(TextureRef::Color(color_to_array(c)));
+                }
+                TextureDesc::Solid {
+                    color: color_to_array(c),
+                }
+            }
+            Texture::Checker(scale, even, odd) => TextureDesc::Checker {
+                scale: *scale,
+                even: self.texture(even)?,
+                odd: self.texture(odd)?,
+            },
+            Texture::Scaled(factor, texture) => TextureDesc::Scaled {
+                factor: color_to_array(factor),
+                texture: self.texture(texture)?,
+            },
+            Texture::Noise(scale, perlin) => TextureDesc::Noise {
+                scale: *scale,
+                perlin: Some(PerlinDesc {
+                    ran_vec: perlin.ran_vec.iter().map(to_array).collect(),
+                    perm_x: perlin.perm_x.clone(),
+                    perm_y: perlin.perm_y.clone(),
+                    perm_z: perlin.perm_z.clone(),
+                }),
+            },
+            Texture::Image(image, path) => {
+                let path = match path {
+                    Some(path) => relative_path(path, &self.dir),
+                    None => {
+                        // Images that weren't loaded from a file are written
+                        // out next to the scene.
+                        let file_name = format!("{}_texture{}.png", self.stem, self.images_written);
+                        let path = self.dir.join(&file_name);
+                        image
+                            .save(&path)
+                            .map_err(|e| SceneError::Image(path.clone(), e))?;
+                        self.images_written += 1;
+                        PathBuf::from(file_name)
+                    }
+                };
+                TextureDesc::Image { path }
+            }
+            Texture::VertexColor => TextureDesc::VertexColor,
+        };
+
+        let count = self.textures.len();
+        match self.shared_name(key, "texture", count) {
+            Some(name) => {
+                self.textures.insert(name.clone(), desc);
+                Ok(TextureRef::Name(name))
+            }
+            None => Ok(TextureRef::Inline(Box::new(desc))),
+        }
+    }
+
+    fn film(&mut self, film: &Option<ThinFilm>) -> Result<Option<FilmDesc>, SceneError> {
+        match film {
+            Some(film) => Ok(Some(FilmDesc {
+                thickness: self.texture(&film.thickness)?,
+                ir: film.ir,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn material(&mut self, material: &Arc<dyn Material>) -> Result<MaterialRef, SceneError> {
+        let key = address(material);
+        if let Some(name) = self.names.get(&key) {
+            return Ok(MaterialRef::Name(name.clone()));
+        }
+
+        let any = material.as_any();
+        let desc = if let Some(m) = any.downcast_ref::<Lambertian>() {
+            MaterialDesc::Lambertian {
+                albedo: self.texture(&m.albedo)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<Metal>() {
+            MaterialDesc::Metal {
+                albedo: color_to_array(&m.albedo),
+                fuzz: m.fuzz,
+            }
+        } else if let Some(m) = any.downcast_ref::<Dielectric>() {
+            MaterialDesc::Dielectric {
+                ir: m.ir,
+                dispersion: m.dispersion.as_ref().map(|d| match d {
+                    Dispersion::Cauchy { a, b } => DispersionDesc::Cauchy { a: *a, b: *b },
+                    Dispersion::Sellmeier { b, c } => DispersionDesc::Sellmeier { b: *b, c: *c },
+                }),
+                film: self.film(&m.film)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<Conductor>() {
+            MaterialDesc::Conductor {
+                preset: None,
+                eta: Some(color_to_array(&m.eta)),
+                k: Some(color_to_array(&m.k)),
+                roughness: m.roughness,
+                film: self.film(&m.film)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<RoughDielectric>() {
+            MaterialDesc::RoughDielectric {
+                ir: m.ir,
+                roughness: m.roughness,
+            }
+        } else if let Some(m) = any.downcast_ref::<Coated>() {
+            MaterialDesc::Coated {
+                base: self.material(&m.base)?,
+                ir: m.ir,
+                roughness: m.roughness,
+                thickness: m.thickness,
+                absorption: color_to_array(&m.absorption),
+            }
+        } else if let Some(m) = any.downcast_ref::<Subsurface>() {
+            MaterialDesc::Subsurface {
+                albedo: self.texture(&m.albedo)?,
+                mean_free_path: color_to_array(&m.mean_free_path),
+                ir: m.ir,
+                anisotropy: m.anisotropy,
+            }
+        } else if let Some(m) = any.downcast_ref::<Mix>() {
+            MaterialDesc::Mix {
+                first: self.material(&m.first)?,
+                second: self.material(&m.second)?,
+                factor: self.texture(&m.factor)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<Bumped>() {
+            match &m.map {
+                BumpMap::Normal(texture, strength) => MaterialDesc::NormalMap {
+                    base: self.material(&m.base)?,
+                    map: self.texture(texture)?,
+                    strength: *strength,
+                },
+                BumpMap::Height(texture, scale) => MaterialDesc::Bump {
+                    base: self.material(&m.base)?,
+                    height: self.texture(texture)?,
+                    scale: *scale,
+                },
+            }
+        } else if let Some(m) = any.downcast_ref::<DiffuseLight>() {
+            MaterialDesc::DiffuseLight {
+                emit: self.texture(&m.emit)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<Isotropic>() {
+            MaterialDesc::Isotropic {
+                albedo: self.texture(&m.albedo)?,
+            }
+        } else if let Some(m) = any.downcast_ref::<Principled>() {
+            MaterialDesc::Principled {
+                base_color: self.texture(&m.base_color)?,
+                metallic: Some(self.texture(&m.metallic)?),
+                roughness: Some(self.texture(&m.roughness)?),
+                specular: Some(self.texture(&m.specular)?),
+                specular_tint: Some(self.texture(&m.specular_tint)?),
+                sheen: Some(self.texture(&m.sheen)?),
+                sheen_tint: Some(self.texture(&m.sheen_tint)?),
+                clearcoat: Some(self.texture(&m.clearcoat)?),
+                clearcoat_gloss: Some(self.texture(&m.clearcoat_gloss)?),
+                transmission: Some(self.texture(&m.transmission)?),
+                ir: m.ir,
+                emission: Some(self.texture(&m.emission)?),
+            }
+        } else {
+            return Err(SceneError::Invalid {
+                context: "materials".to_string(),
+                message: "the scene uses a material that can't be saved".to_string(),
+            });
+        };
+
+        let count = self.materials.len();
+        match self.shared_name(key, "material", count) {
+            Some(name) => {
+                self.materials.insert(name.clone(), desc);
+                Ok(MaterialRef::Name(name))
+            }
+            None => Ok(MaterialRef::Inline(Box::new(desc))),
+        }
+    }
+
+    fn object(&mut self, object: &Arc<Hittable>) -> Result<ObjectRef, SceneError> {
+        let key = address(object);
+        if let Some(name) = self.names.get(&key) {
+            return Ok(ObjectRef::Name(name.clone()));
+        }
+
+        let desc = match &**object {
+            Hittable::Sphere(s) => ObjectDesc::Sphere {
+                center: to_array(&s.center),
+                radius: s.radius,
+                material: self.material(&s.material)?,
+            },
+            Hittable::MovingSphere(s) => ObjectDesc::MovingSphere {
+                center0: to_array(&s.center0),
+                center1: to_array(&s.center1),
+                time0: s.time0,
+                time1: s.time1,
+                radius: s.radius,
+                material: self.material(&s.material)?,
+            },
+            Hittable::Node(n) => {
+                let objects = bvh_leaves(n)
+                    .iter()
+                    .map(|leaf| self.object(leaf))
+                    .collect::<Result<_, _>>()?;
+                ObjectDesc::Bvh { objects }
+            }
+            Hittable::Bvh(bvh) => {
+                let objects = bvh
+                    .objects
+                    .iter()
+                    .map(|object| self.object(object))
+                    .collect::<Result<_, _>>()?;
+                ObjectDesc::Bvh { objects }
+            }
+            // Only BVH leaves are lists, so it becomes one again on loading.
+            Hittable::List(list) => {
+                let objects = list
+                    .objects
+                    .iter()
+                    .map(|object| self.object(object))
+                    .collect::<Result<_, _>>()?;
+                ObjectDesc::Bvh { objects }
+            }
+            Hittable::XYRect(r) => ObjectDesc::XyRect {
+                x0: r.x0,
+                x1: r.x1,
+                y0: r.y0,
+                y1: r.y1,
+                k: r.k,
+                material: self.material(&r.mp)?,
+            },
+            Hittable::XZRect(r) => ObjectDesc::XzRect {
+                x0: r.x0,
+                x1: r.x1,
+                z0: r.z0,
+                z1: r.z1,
+                k: r.k,
+                material: self.material(&r.mp)?,
+            },
+            Hittable::YZRect(r) => ObjectDesc::YzRect {
+                y0: r.y0,
+                y1: r.y1,
+                z0: r.z0,
+                z1: r.z1,
+                k: r.k,
+                material: self.material(&r.mp)?,
+            },
+            Hittable::Box(b) => ObjectDesc::Box {
+                min: to_array(&b.min),
+                max: to_array(&b.max),
+                material: self.material(&b.material)?,
+            },
+            Hittable::Translate(t) => ObjectDesc::Translate {
+                offset: to_array(&t.offset),
+                object: self.object(&t.ptr)?,
+            },
+            Hittable::RotateY(r) => ObjectDesc::RotateY {
+                angle: r.angle,
+                object: self.object(&r.ptr)?,
+            },
+            Hittable::ConstantMedium(m) => {
+                let albedo = match m.phase_function.as_any().downcast_ref::<Isotropic>() {
+                    Some(phase) => self.texture(&phase.albedo)?,
+                    None => {
+                        return Err(SceneError::Invalid {
+                            context: "shapes".to_string(),
+                            message: "constant medium with a non-isotropic phase function"
+                                .to_string(),
+                        })
+                    }
+                };
+                ObjectDesc::ConstantMedium {
+                    boundary: self.object(&m.boundary)?,
+                    density: -1.0 / m.neg_inv_density,
+                    albedo,
+                }
+            }
+            Hittable::Triangle(t) => {
+                let v = &t.vertices;
+                let [i0, i1, i2] = t.indices;
+                ObjectDesc::Triangle {
+                    vertices: [i0, i1, i2].map(|i| to_array(&v.positions[i])),
+                    normals: (!v.normals.is_empty())
+                        .then(|| [i0, i1, i2].map(|i| to_array(&v.normals[i]))),
+                    uvs: (!v.uvs.is_empty()).then(|| [i0, i1, i2].map(|i| to_array2(&v.uvs[i]))),
+                    material: self.material(&t.material)?,
+                }
+            }
+            Hittable::Mesh(m) => ObjectDesc::Mesh {
+                positions: m.vertices.positions.iter().map(to_array).collect(),
+                normals: m.vertices.normals.iter().map(to_array).collect(),
+                uvs: m.vertices.uvs.iter().map(to_array2).collect(),
+                colors: m.vertices.colors.iter().map(color_to_array).collect(),
+                indices: m.indices.clone(),
+                material: self.material(&m.material)?,
+            },
+            Hittable::Transform(t) => ObjectDesc::Transform {
+                object: self.object(&t.ptr)?,
+                scale: None,
+                rotate: None,
+                translate: None,
+                matrix: Some(t.matrix.m),
+            },
+            Hittable::AnimatedTransform(t) => ObjectDesc::AnimatedTransform {
+                object: self.object(&t.ptr)?,
+                keyframes: t
+                    .keyframes
+                    .iter()
+                    .map(|k| KeyframeDesc {
+                        time: k.time,
+                        scale: Some(to_array(&k.scale)),
+                        rotate: None,
+                        rotation: Some(k.rotation),
+                        translate: Some(to_array(&k.translation)),
+                    })
+                    .collect(),
+            },
+            Hittable::Instance(inst) => ObjectDesc::Instance {
+                object: self.object(&inst.object)?,
+                transform: inst.transform.m,
+                material: match &inst.material {
+                    Some(material) => Some(self.material(material)?),
+                    None => None,
+                },
+            },
+        };
+
+        let count = self.shapes.len();
+        match self.shared_name(key, "shape", count) {
+            Some(name) => {
+                self.shapes.insert(name.clone(), desc);
+                Ok(ObjectRef::Name(name))
+            }
+            None => Ok(ObjectRef::Inline(Box::new(desc))),
+        }
+    }
+}
+
+// Puts arrays that hold nothing but numbers (points, colors, lookup tables) on a
+// single line, which keeps saved scenes short and easy to diff.
+fn collapse_number_arrays(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut chars = json.char_indices().peekable();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        if c == '"' {
+            in_string = true;
+        } else if c == '[' {
+            let end = json[i..].find(']').map(|e| i + e);
+            if let Some(end) = end {
+                let inner = &json[i + 1..end];
+                let numeric = inner
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c.is_whitespace() || ",.-+eE".contains(c));
+                if numeric && !inner.trim().is_empty() {
+                    let items: Vec<&str> = inner.split(',').map(str::trim).collect();
+                    out.push('[');
+                    out.push_str(&items.join(", "));
+                    out.push(']');
+                    while let Some(&(j, _)) = chars.peek() {
+                        if j > end {
+                            break;
+                        }
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+pub fn save(scene: &Scene, path: &Path) -> Result<(), SceneError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scene".to_string());
+
+    let mut exporter = Exporter {
+        dir,
+        stem,
+        uses: HashMap::new(),
+        names: HashMap::new(),
+        textures: BTreeMap::new(),
+        materials: BTreeMap::new(),
+        shapes: BTreeMap::new(),
+        images_written: 0,
+    };
+
+    for object in &scene.world.objects {
+        exporter.count_object(object);
+    }
+    let objects = scene
+        .world
+        .objects
+        .iter()
+        .map(|object| exporter.object(object))
+        .collect::<Result<_, _>>()?;
+
+    let desc = SceneDesc {
+        camera: CameraDesc {
+            look_from: to_array(&scene.look_from),
+            look_at: to_array(&scene.look_at),
+            vup: to_array(&scene.vup),
+            vfov: scene.vfov,
+            aperture: scene.aperture,
+            focus_dist: scene.focus_dist,
+        },
+        image: ImageDesc {
+            width: scene.image_width,
+            aspect_ratio: scene.aspect_ratio,
+            samples_per_pixel: scene.samples_per_pixel,
+        },
+        background: color_to_array(&scene.background),
+        textures: exporter.textures,
+        materials: exporter.materials,
+        shapes: exporter.shapes,
+        objects,
+    };
+
+    let json = serde_json::to_string_pretty(&desc)
+        .map_err(|e| SceneError::Parse(path.to_path_buf(), e))?;
+    fs::write(path, collapse_number_arrays(&json) + "\n")
+        .map_err(|e| SceneError::Io(path.to_path_buf(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("parto-test-{}.json", name))
+    }
+
+    fn load_text(name: &str, text: &str) -> Result<Scene, SceneError> {
+        let path = temp_path(name);
+        fs::write(&path, text).unwrap();
+        let result = load(&path, &mut Sampler::new(0));
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn sphere_material(scene: &Scene, i: usize) -> &Arc<dyn Material> {
+        match &*scene.world.objects[i] {
+            Hittable::Sphere(s) => &s.material,
+            _ => panic!("object {} is not a sphere", i),
+        }
+    }
+
+    fn albedo(material: &Arc<dyn Material>) -> &Arc<Texture> {
+        &material
+            .as_any()
+            .downcast_ref::<Lambertian>()
+            .expect("not a lambertian")
+            .albedo
+    }
+
+    #[test]
+    fn saving_keeps_shared_definitions_shared() {
+        let scene = load_text(
+            "shared",
+            r#"{
+                "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+                "textures": {
+                    "checker": { "type": "checker", "scale": 10, "even": 0.2, "odd": 0.9 }
+                },
+                "materials": {
+                    "ground": { "type": "lambertian", "albedo": "checker" },
+                    "other": { "type": "lambertian", "albedo": "checker" }
+                },
+                "objects": [
+                    { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "ground" },
+                    { "type": "sphere", "center": [2, 0, 0], "radius": 1, "material": "ground" },
+                    { "type": "sphere", "center": [4, 0, 0], "radius": 1, "material": "other" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let path = temp_path("shared-saved");
+        save(&scene, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let reloaded = load(&path, &mut Sampler::new(0));
+        fs::remove_file(&path).unwrap();
+
+        // One definition each for the texture and the material used twice, with
+        // both spheres referring to the same name.
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["textures"].as_object().unwrap().len(), 1);
+        assert_eq!(json["materials"].as_object().unwrap().len(), 1);
+        assert!(json["objects"][0]["material"].is_string());
+        assert_eq!(
+            json["objects"][0]["material"],
+            json["objects"][1]["material"]
+        );
+
+        let reloaded = reloaded.unwrap();
+        assert!(Arc::ptr_eq(
+            sphere_material(&reloaded, 0),
+            sphere_material(&reloaded, 1)
+        ));
+        assert!(!Arc::ptr_eq(
+            sphere_material(&reloaded, 0),
+            sphere_material(&reloaded, 2)
+        ));
+        assert!(Arc::ptr_eq(
+            albedo(sphere_material(&reloaded, 0)),
+            albedo(sphere_material(&reloaded, 2))
+        ));
+    }
+
+    #[test]
+    fn definitions_referring_to_themselves_are_errors() {
+        let result = load_text(
+            "cycle",
+            r#"{
+                "camera": { "look_from": [0, 0, 5], "look_at": [0, 0, 0] },
+                "textures": {
+                    "a": { "type": "checker", "scale": 1, "even": "b", "odd": 0.5 },
+                    "b": { "type": "checker", "scale": 1, "even": 0.5, "odd": "a" }
+                },
+                "objects": [
+                    {
+                        "type": "sphere", "center": [0, 0, 0], "radius": 1,
+                        "material": { "type": "lambertian", "albedo": "a" }
+                    }
+                ]
+            }"#,
+        );
+
+        match result {
+            Err(SceneError::Cycle { kind, name }) => {
+                assert_eq!(kind, "texture");
+                assert_eq!(name, "a");
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("the cycle was accepted"),
+        }
+    }
+}
@@ -0,0 +1,735 @@
+use crate::{
+    color::Color,
+    constant_medium::ConstantMedium,
+    dielectric::Dielectric,
+    diffuse_light::DiffuseLight,
+    geo_box::GeoBox,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    instance::Instance,
+    lambertian::Lambertian,
+    linear_bvh::LinearBVH,
+    mat4::Mat4,
+    material::Material,
+    metal::Metal,
+    moving_sphere::MovingSphere,
+    perlin::Perlin,
+    rect::{XYRect, XZRect, YZRect},
+    sampler::Sampler,
+    sphere::Sphere,
+    texture::Texture,
+    transform::Transform,
+    triangle::{MeshVertices, TriangleMesh},
+};
+use cliffy::{Vec3, Vector};
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+
+pub const NAMES: [&str; 9] = [
+    "random_scene",
+    "two_spheres",
+    "two_perlin_spheres",
+    "earth",
+    "simple_light",
+    "cornell_box",
+    "cornell_smoke",
+    "final_scene",
+    "forest",
+];
+
+// A world together with the camera and image settings it was set up to be
+// rendered with. All of these can be overridden from the command line.
+pub struct Scene {
+    pub world: HittableList,
+    pub background: Color,
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus_dist: f32,
+    pub aspect_ratio: f32,
+    pub image_width: u32,
+    pub samples_per_pixel: u32,
+}
+
+pub fn build(name: &str, sampler: &mut Sampler) -> Option<Scene> {
+    let mut scene = Scene {
+        world: HittableList::empty(),
+        background: Color::black(),
+        look_from: Vec3::zero(),
+        look_at: Vec3::zero(),
+        vup: Vec3::up(),
+        vfov: 40.0,
+        aperture: 0.0,
+        focus_dist: 10.0,
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+    };
+
+    match name {
+        "random_scene" => {
+            scene.world = random_scene(sampler);
+            scene.background = Color::new(0.7, 0.8, 1.0);
+            scene.look_from = Vec3::new(13.0, 2.0, 3.0);
+            scene.look_at = Vec3::new(0.0, 0.0, 0.0);
+            scene.vfov = 20.0;
+            scene.aperture = 0.1;
+        }
+
+        "two_spheres" => {
+            scene.world = two_spheres();
+            scene.background = Color::new(0.7, 0.8, 1.0);
+            scene.look_from = Vec3::new(13.0, 2.0, 3.0);
+            scene.look_at = Vec3::new(0.0, 0.0, 0.0);
+            scene.vfov = 20.0;
+        }
+
+        "two_perlin_spheres" => {
+            scene.world = two_perlin_spheres(sampler);
+            scene.background = Color::new(0.7, 0.8, 1.0);
+            scene.look_from = Vec3::new(13.0, 2.0, 3.0);
+            scene.look_at = Vec3::new(0.0, 0.0, 0.0);
+            scene.vfov = 20.0;
+        }
+
+        "earth" => {
+            scene.world = earth();
+            scene.background = Color::new(0.7, 0.8, 1.0);
+            scene.look_from = Vec3::new(13.0, 2.0, 3.0);
+            scene.look_at = Vec3::new(0.0, 0.0, 0.0);
+            scene.vfov = 20.0;
+        }
+
+        "simple_light" => {
+            scene.world = simple_light(sampler);
+            scene.samples_per_pixel = 400;
+            scene.background = Color::black();
+            scene.look_from = Vec3::new(26.0, 3.0, 6.0);
+            scene.look_at = Vec3::new(0.0, 2.0, 0.0);
+            scene.vfov = 20.0;
+        }
+
+        "cornell_box" => {
+            scene.world = cornell_box();
+            scene.aspect_ratio = 1.0;
+            scene.image_width = 600;
+            scene.samples_per_pixel = 200;
+            scene.background = Color::black();
+            scene.look_from = Vec3::new(278.0, 278.0, -800.0);
+            scene.look_at = Vec3::new(278.0, 278.0, 0.0);
+            scene.vfov = 40.0;
+        }
+
+        "cornell_smoke" => {
+            scene.world = cornell_smoke();
+            scene.aspect_ratio = 1.0;
+            scene.image_width = 600;
+            scene.samples_per_pixel = 200;
+            scene.background = Color::black();
+            scene.look_from = Vec3::new(278.0, 278.0, -800.0);
+            scene.look_at = Vec3::new(278.0, 278.0, 0.0);
+            scene.vfov = 40.0;
+        }
+
+        "final_scene" => {
+            scene.world = final_scene(sampler);
+            scene.aspect_ratio = 1.0;
+            scene.image_width = 800;
+            scene.samples_per_pixel = 10000;
+            scene.background = Color::black();
+            scene.look_from = Vec3::new(478.0, 278.0, -600.0);
+            scene.look_at = Vec3::new(278.0, 278.0, 0.0);
+            scene.vfov = 40.0;
+        }
+
+        "forest" => {
+            scene.world = forest(sampler);
+            scene.background = Color::new(0.7, 0.8, 1.0);
+            scene.look_from = Vec3::new(0.0, 8.0, -60.0);
+            scene.look_at = Vec3::new(0.0, 0.0, 0.0);
+            scene.vfov = 30.0;
+        }
+
+        _ => return None,
+    }
+
+    Some(scene)
+}
+
+fn random_scene(sampler: &mut Sampler) -> HittableList {
+    let mut world = HittableList::empty();
+
+    let checker = Arc::new(Texture::Checker(
+        10.0,
+        Arc::new(Texture::SolidColor(Color::new(0.2, 0.3, 0.1))),
+        Arc::new(Texture::SolidColor(Color::new(0.9, 0.9, 0.9))),
+    ));
+
+    let ground_material = Arc::new(Lambertian::new(checker));
+    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material.clone(),
+    ))));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = sampler.random_float();
+            let center = Vec3::new(
+                a as f32 + 0.9 * sampler.random_float(),
+                0.2,
+                b as f32 + 0.9 * sampler.random_float(),
+            );
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).mag() > 0.9 {
+                if choose_mat < 0.8 {
+                    // diffuse
+                    let mut albedo = sampler.random_color();
+                    let random_albedo = sampler.random_color();
+                    albedo *= random_albedo;
+                    let center2 =
+                        center + Vec3::new(0.0, sampler.random_float_between(0.0, 0.5), 0.0);
+                    world.add(Arc::new(Hittable::MovingSphere(MovingSphere::new(
+                        center,
+                        center2,
+                        0.0,
+                        1.0,
+                        0.2,
+                        Arc::new(Lambertian::with_color(albedo)),
+                    ))));
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = sampler.random_color_between(0.5, 1.0);
+                    let fuzz = sampler.random_float_between(0.0, 0.5);
+                    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+                        center,
+                        0.2,
+                        Arc::new(Metal::new(albedo, fuzz)),
+                    ))));
+                } else {
+                    // glass
+                    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+                        center,
+                        0.2,
+                        Arc::new(Dielectric::new(1.5)),
+                    ))));
+                }
+            }
+        }
+    }
+
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    ))));
+
+    let material2 = Arc::new(Lambertian::with_color(Color::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    ))));
+
+    let material3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    ))));
+
+    world
+}
+
+fn two_spheres() -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let checker = Arc::new(Texture::Checker(
+        10.0,
+        Arc::new(Texture::SolidColor(Color::new(0.2, 0.3, 0.1))),
+        Arc::new(Texture::SolidColor(Color::new(0.9, 0.9, 0.9))),
+    ));
+
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, -10.0, 0.0),
+        10.0,
+        Arc::new(Lambertian::new(checker.clone())),
+    ))));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 10.0, 0.0),
+        10.0,
+        Arc::new(Lambertian::new(checker.clone())),
+    ))));
+
+    objects
+}
+
+fn two_perlin_spheres(sampler: &mut Sampler) -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let per_text = Arc::new(Texture::Noise(4.0, Perlin::new(sampler)));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(per_text.clone())),
+    ))));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(Lambertian::new(per_text.clone())),
+    ))));
+
+    objects
+}
+
+fn earth() -> HittableList {
+    let earth_texture = Arc::new(Texture::Image(
+        ImageReader::open("res/earthmap.jpg")
+            .unwrap()
+            .decode()
+            .unwrap(),
+        Some("res/earthmap.jpg".into()),
+    ));
+    let earth_surface = Arc::new(Lambertian::new(earth_texture.clone()));
+    let globe = Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        2.0,
+        earth_surface.clone(),
+    )));
+
+    HittableList::new(globe)
+}
+
+fn simple_light(sampler: &mut Sampler) -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let pertext = Arc::new(Texture::Noise(4.0, Perlin::new(sampler)));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(pertext.clone())),
+    ))));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(Lambertian::new(pertext.clone())),
+    ))));
+
+    let diff_light = Arc::new(DiffuseLight::with_color(Color::new(4.0, 4.0, 4.0)));
+    objects.add(Arc::new(Hittable::XYRect(XYRect::new(
+        diff_light.clone(),
+        3.0,
+        5.0,
+        1.0,
+        3.0,
+        -2.0,
+    ))));
+
+    objects
+}
+
+fn cornell_box() -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let red = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.65, 0.05, 0.05,
+    )))));
+    let white = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.73, 0.73, 0.73,
+    )))));
+    let green = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.12, 0.45, 0.15,
+    )))));
+    let light = Arc::new(DiffuseLight::new(Arc::new(Texture::SolidColor(
+        Color::new(15.0, 15.0, 15.0),
+    ))));
+
+    objects.add(Arc::new(Hittable::YZRect(YZRect::new(
+        green.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+    objects.add(Arc::new(Hittable::YZRect(YZRect::new(
+        red.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        light.clone(),
+        213.0,
+        343.0,
+        227.0,
+        332.0,
+        554.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+    objects.add(Arc::new(Hittable::XYRect(XYRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+
+    let mut box1 = Arc::new(Hittable::Box(GeoBox::new(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    )));
+    box1 = Arc::new(Hittable::Transform(Transform::new(
+        box1,
+        Mat4::translation(&Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotation_axis(&Vec3::up(), 15.0),
+    )));
+    objects.add(box1);
+
+    let mut box2 = Arc::new(Hittable::Box(GeoBox::new(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 165.0, 165.0),
+        white.clone(),
+    )));
+    box2 = Arc::new(Hittable::Transform(Transform::new(
+        box2,
+        Mat4::translation(&Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotation_axis(&Vec3::up(), -18.0),
+    )));
+    objects.add(box2);
+
+    objects
+}
+
+fn cornell_smoke() -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let red = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.65, 0.05, 0.05,
+    )))));
+    let white = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.73, 0.73, 0.73,
+    )))));
+    let green = Arc::new(Lambertian::new(Arc::new(Texture::SolidColor(Color::new(
+        0.12, 0.45, 0.15,
+    )))));
+    let light = Arc::new(DiffuseLight::new(Arc::new(Texture::SolidColor(
+        Color::new(15.0, 15.0, 15.0),
+    ))));
+
+    objects.add(Arc::new(Hittable::YZRect(YZRect::new(
+        green.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+    objects.add(Arc::new(Hittable::YZRect(YZRect::new(
+        red.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        light.clone(),
+        213.0,
+        343.0,
+        227.0,
+        332.0,
+        554.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+    ))));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+    objects.add(Arc::new(Hittable::XYRect(XYRect::new(
+        white.clone(),
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+    ))));
+
+    let mut box1 = Arc::new(Hittable::Box(GeoBox::new(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    )));
+    box1 = Arc::new(Hittable::Transform(Transform::new(
+        box1,
+        Mat4::translation(&Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotation_axis(&Vec3::up(), 15.0),
+    )));
+
+    let mut box2 = Arc::new(Hittable::Box(GeoBox::new(
+        &Vec3::new(0.0, 0.0, 0.0),
+        &Vec3::new(165.0, 165.0, 165.0),
+        white.clone(),
+    )));
+    box2 = Arc::new(Hittable::Transform(Transform::new(
+        box2,
+        Mat4::translation(&Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotation_axis(&Vec3::up(), -18.0),
+    )));
+
+    objects.add(Arc::new(Hittable::ConstantMedium(
+        ConstantMedium::with_color(box1, 0.01, Color::black()),
+    )));
+    objects.add(Arc::new(Hittable::ConstantMedium(
+        ConstantMedium::with_color(box2, 0.01, Color::white()),
+    )));
+
+    objects
+}
+
+fn final_scene(sampler: &mut Sampler) -> HittableList {
+    let mut boxes1 = HittableList::empty();
+    let ground = Arc::new(Lambertian::with_color(Color::new(0.48, 0.83, 0.53)));
+
+    let boxes_per_side = 20;
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f32 * w;
+            let z0 = -1000.0 + j as f32 * w;
+            let y0 = 0.0;
+            let x1 = x0 + w;
+            let y1 = sampler.random_float_between(1.0, 101.0);
+            let z1 = z0 + w;
+
+            boxes1.add(Arc::new(Hittable::Box(GeoBox::new(
+                &Vec3::new(x0, y0, z0),
+                &Vec3::new(x1, y1, z1),
+                ground.clone(),
+            ))));
+        }
+    }
+
+    let mut objects = HittableList::empty();
+
+    objects.add(Arc::new(Hittable::Bvh(LinearBVH::new(
+        &mut boxes1,
+        0.0,
+        1.0,
+    ))));
+
+    let light = Arc::new(DiffuseLight::with_color(Color::white() * 7.0));
+    objects.add(Arc::new(Hittable::XZRect(XZRect::new(
+        light.clone(),
+        123.0,
+        423.0,
+        147.0,
+        412.0,
+        554.0,
+    ))));
+
+    let center1 = Vec3::new(400.0, 400.0, 200.0);
+    let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
+    let moving_sphere_mat = Arc::new(Lambertian::with_color(Color::new(0.7, 0.3, 0.1)));
+    objects.add(Arc::new(Hittable::MovingSphere(MovingSphere::new(
+        center1,
+        center2,
+        0.0,
+        1.0,
+        50.0,
+        moving_sphere_mat.clone(),
+    ))));
+
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(260.0, 150.0, 45.0),
+        50.0,
+        Arc::new(Dielectric::new(1.5)),
+    ))));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, 150.0, 145.0),
+        50.0,
+        Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 1.0)),
+    ))));
+
+    let boundary = Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(360.0, 150.0, 145.0),
+        70.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    objects.add(boundary.clone());
+    objects.add(Arc::new(Hittable::ConstantMedium(
+        ConstantMedium::with_color(boundary.clone(), 0.2, Color::new(0.2, 0.4, 0.9)),
+    )));
+
+    let boundary = Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::zero(),
+        5000.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    objects.add(Arc::new(Hittable::ConstantMedium(
+        ConstantMedium::with_color(boundary, 0.0001, Color::white()),
+    )));
+
+    let emat = Arc::new(Lambertian::new(Arc::new(Texture::Image(
+        ImageReader::open("res/earthmap.jpg")
+            .unwrap()
+            .decode()
+            .unwrap(),
+        Some("res/earthmap.jpg".into()),
+    ))));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(400.0, 200.0, 400.0),
+        100.0,
+        emat,
+    ))));
+    let pertext = Arc::new(Texture::Noise(0.1, Perlin::new(sampler)));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(220.0, 280.0, 300.0),
+        80.0,
+        Arc::new(Lambertian::new(pertext)),
+    ))));
+
+    let mut boxes2 = HittableList::empty();
+    let white = Arc::new(Lambertian::with_color(Color::new(0.73, 0.73, 0.73)));
+    let ns = 1000;
+    for _ in 0..ns {
+        boxes2.add(Arc::new(Hittable::Sphere(Sphere::new(
+            sampler.random_vec3_between(0.0, 165.0),
+            10.0,
+            white.clone(),
+        ))));
+    }
+
+    let node = Hittable::Bvh(LinearBVH::new(&mut boxes2, 0.0, 1.0));
+    objects.add(Arc::new(Hittable::Transform(Transform::new(
+        Arc::new(node),
+        Mat4::translation(&Vec3::new(-100.0, 270.0, 395.0))
+            * Mat4::rotation_axis(&Vec3::up(), 15.0),
+    ))));
+
+    objects
+}
+
+// Thousands of trees that are all the same two meshes, placed by instances.
+fn forest(sampler: &mut Sampler) -> HittableList {
+    let mut objects = HittableList::empty();
+
+    let ground = Arc::new(Lambertian::with_color(Color::new(0.35, 0.45, 0.2)));
+    objects.add(Arc::new(Hittable::Sphere(Sphere::new(
+        Vec3::new(0.0, -10000.0, 0.0),
+        10000.0,
+        ground,
+    ))));
+
+    let bark = Arc::new(Lambertian::with_color(Color::new(0.35, 0.22, 0.1)));
+    let trunk = Arc::new(Hittable::Mesh(lathe(
+        &[(0.0, 0.0), (0.15, 0.0), (0.15, 0.8), (0.0, 0.8)],
+        6,
+        bark,
+    )));
+    let needles = Arc::new(Lambertian::with_color(Color::new(0.1, 0.35, 0.12)));
+    let crown = Arc::new(Hittable::Mesh(lathe(
+        &[(0.0, 0.6), (1.0, 0.6), (0.6, 1.6), (0.0, 3.0)],
+        12,
+        needles,
+    )));
+    let autumn: Arc<dyn Material> = Arc::new(Lambertian::with_color(Color::new(0.8, 0.4, 0.1)));
+
+    let mut trees = HittableList::empty();
+    for i in -40..40 {
+        for j in -40..40 {
+            let position = Vec3::new(
+                i as f32 + 0.8 * sampler.random_float(),
+                0.0,
+                j as f32 + 0.8 * sampler.random_float(),
+            );
+            let angle = sampler.random_float_between(0.0, std::f32::consts::PI);
+            let rotation = [0.0, angle.sin(), 0.0, angle.cos()];
+            let scale = Vec3::uni(sampler.random_float_between(0.3, 0.6));
+            let transform = Mat4::from_trs(&position, rotation, &scale);
+
+            let crown_material = if sampler.random_float() < 0.2 {
+                Some(autumn.clone())
+            } else {
+                None
+            };
+
+            trees.add(Arc::new(Hittable::Instance(Instance::new(
+                trunk.clone(),
+                transform,
+                None,
+            ))));
+            trees.add(Arc::new(Hittable::Instance(Instance::new(
+                crown.clone(),
+                transform,
+                crown_material,
+            ))));
+        }
+    }
+    objects.add(Arc::new(Hittable::Bvh(LinearBVH::new(
+        &mut trees, 0.0, 1.0,
+    ))));
+
+    objects
+}
+
+// Sweeps a profile of (radius, height) points around the Y axis.
+fn lathe(profile: &[(f32, f32)], segments: usize, material: Arc<dyn Material>) -> TriangleMesh {
+    let mut positions = Vec::new();
+    for &(radius, y) in profile {
+        for s in 0..segments {
+            let phi = 2.0 * std::f32::consts::PI * s as f32 / segments as f32;
+            positions.push(Vec3::new(radius * phi.cos(), y, radius * phi.sin()));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for (ring, pair) in profile.windows(2).enumerate() {
+        for s in 0..segments {
+            let a = ring * segments + s;
+            let b = ring * segments + (s + 1) % segments;
+            let (c, d) = (a + segments, b + segments);
+
+            // Rings of radius 0 are a single point, which leaves one triangle.
+            if pair[0].0 > 0.0 {
+                indices.push([a, b, c]);
+            }
+            if pair[1].0 > 0.0 {
+                indices.push([b, d, c]);
+            }
+        }
+    }
+
+    let vertices = MeshVertices::new(positions, Vec::new(), Vec::new());
+    TriangleMesh::new(Arc::new(vertices), indices, material)
+}
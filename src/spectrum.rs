@@ -0,0 +1,125 @@
+use crate::color::Color;
+
+// Visible range, in nanometers.
+pub const LAMBDA_MIN: f32 = 380.0;
+pub const LAMBDA_MAX: f32 = 780.0;
+
+// Where the basis spectra `upsample` builds on cross over from blue to green and
+// from green to red, and how wide the crossings are.
+const BLUE_GREEN: f32 = 485.0;
+const GREEN_RED: f32 = 590.0;
+const CROSSOVER_WIDTH: f32 = 50.0;
+
+// Wavelengths the red, green and blue channels stand for, when a quantity only
+// known in RGB is needed at some wavelength.
+const CHANNEL_LAMBDAS: [f32; 3] = [650.0, 550.0, 450.0];
+
+// The CIE 1931 color matching functions at `lambda`, after the multi-lobe fit of
+// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions"
+// (Wyman et al. 2013).
+pub fn xyz_matching(lambda: f32) -> [f32; 3] {
+    let g = |mu: f32, sigma1: f32, sigma2: f32| {
+        let t = (lambda - mu) / if lambda < mu { sigma1 } else { sigma2 };
+        (-0.5 * t * t).exp()
+    };
+
+    [
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    ]
+}
+
+// Linear sRGB.
+pub fn xyz_to_rgb(xyz: &[f32; 3]) -> Color {
+    let [x, y, z] = *xyz;
+    Color::new(
+        3.240_454 * x - 1.537_139 * y - 0.498_531 * z,
+        -0.969_266 * x + 1.876_011 * y + 0.041_556 * z,
+        0.055_643 * x - 0.204_026 * y + 1.057_225 * z,
+    )
+}
+
+// The color of a reflectance spectrum, given as a function of wavelength, under
+// light that is equally strong at every wavelength. It's taken at `samples`
+// evenly spaced wavelengths, and a spectrum that is 1 everywhere comes out
+// white.
+pub fn reflectance_to_rgb(samples: usize, reflectance: impl Fn(f32) -> f32) -> Color {
+    let step = (LAMBDA_MAX - LAMBDA_MIN) / samples as f32;
+    let mut xyz = [0.0; 3];
+    let mut white = [0.0; 3];
+    for i in 0..samples {
+        let lambda = LAMBDA_MIN + (i as f32 + 0.5) * step;
+        let matching = xyz_matching(lambda);
+        let r = reflectance(lambda);
+        for c in 0..3 {
+            xyz[c] += r * matching[c];
+            white[c] += matching[c];
+        }
+    }
+
+    let rgb = xyz_to_rgb(&xyz);
+    let white = xyz_to_rgb(&white);
+    Color::new(rgb.r / white.r, rgb.g / white.g, rgb.b / white.b)
+}
+
+// A smooth spectrum with about the color `c`, at `lambda`. It's a blend of
+// three basis spectra, one for each channel, that add up to 1 everywhere, so
+// grays stay flat and colors between 0 and 1 stay between 0 and 1 as well.
+pub fn upsample(c: &Color, lambda: f32) -> f32 {
+    let smoothstep = |edge: f32| {
+        let t = ((lambda - edge) / CROSSOVER_WIDTH + 0.5).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    };
+
+    let red = smoothstep(GREEN_RED);
+    let blue = 1.0 - smoothstep(BLUE_GREEN);
+    c.r * red + c.g * (1.0 - red - blue) + c.b * blue
+}
+
+// Color of the whole visible range at strength 1, summed nanometer by
+// nanometer, which is what `wavelength_to_rgb` divides by.
+pub fn white() -> Color {
+    let mut xyz = [0.0; 3];
+    let mut lambda = LAMBDA_MIN + 0.5;
+    while lambda < LAMBDA_MAX {
+        let matching = xyz_matching(lambda);
+        for c in 0..3 {
+            xyz[c] += matching[c];
+        }
+        lambda += 1.0;
+    }
+
+    xyz_to_rgb(&xyz)
+}
+
+// What a sample of `value` at `lambda`, picked uniformly from the visible range,
+// adds to a pixel, so that a spectrum flat at 1 averages out to white.
+pub fn wavelength_to_rgb(lambda: f32, value: f32, white: &Color) -> Color {
+    let rgb = xyz_to_rgb(&xyz_matching(lambda)) * (value * (LAMBDA_MAX - LAMBDA_MIN));
+    Color::new(rgb.r / white.r, rgb.g / white.g, rgb.b / white.b)
+}
+
+// Reads a value given per channel at `lambda`, interpolating between the
+// wavelengths the channels stand for.
+pub fn channel_at(c: &Color, lambda: f32) -> f32 {
+    if lambda >= CHANNEL_LAMBDAS[0] {
+        return c.r;
+    }
+    if lambda <= CHANNEL_LAMBDAS[2] {
+        return c.b;
+    }
+
+    let (i, t) = if lambda >= CHANNEL_LAMBDAS[1] {
+        (
+            0,
+            (CHANNEL_LAMBDAS[0] - lambda) / (CHANNEL_LAMBDAS[0] - CHANNEL_LAMBDAS[1]),
+        )
+    } else {
+        (
+            1,
+            (CHANNEL_LAMBDAS[1] - lambda) / (CHANNEL_LAMBDAS[1] - CHANNEL_LAMBDAS[2]),
+        )
+    };
+    c[i] + (c[i + 1] - c[i]) * t
+}
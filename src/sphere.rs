@@ -1,15 +1,17 @@
-use crate::{aabb::AABB, hittable::HitRecord, material::Material, Ray};
+use crate::{
+    aabb::AABB, hittable::HitRecord, material::Material, sampler::Sampler, utilities, Ray,
+};
 use cliffy::{Vec2, Vec3, Vector};
-use std::rc::Rc;
+use std::{f32::consts::PI, sync::Arc};
 
 pub struct Sphere {
-    center: Vec3,
-    radius: f32,
-    material: Rc<dyn Material>,
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, material: Rc<dyn Material>) -> Self {
+    pub fn new(center: Vec3, radius: f32, material: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius,
@@ -17,7 +19,13 @@ impl Sphere {
         }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
         let oc = r.origin - self.center;
         let a = r.direction.mag_sq();
         let half_b = oc.dot(r.direction);
@@ -48,6 +56,9 @@ impl Sphere {
         let outward_normal = (rec.point - self.center) / self.radius;
         rec.set_face_normal(r, &outward_normal);
         rec.uv = Self::get_uv(&outward_normal);
+        let (dpdu, dpdv) = self.get_tangents(&outward_normal);
+        rec.dpdu = dpdu;
+        rec.dpdv = dpdv;
 
         Some(rec)
     }
@@ -60,6 +71,49 @@ impl Sphere {
         })
     }
 
+    // Density, over solid angle, of `random` heading from `origin` in direction
+    // `v`.
+    pub fn pdf_value(&self, origin: &Vec3, v: &Vec3, sampler: &mut Sampler) -> f32 {
+        let distance_squared = (self.center - *origin).mag_sq();
+        let radius_squared = self.radius * self.radius;
+        // From inside, every direction leads to the sphere.
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * PI);
+        }
+
+        if self
+            .hit(&Ray::new(*origin, *v), 0.001, f32::INFINITY, sampler)
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    // A direction from `origin` towards the sphere, uniform over the cone it
+    // takes up as seen from there.
+    pub fn random(&self, origin: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.mag_sq();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return sampler.random_unit_vec3();
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let z = 1.0 + sampler.random_float() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * sampler.random_float();
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        let w = direction.normalized();
+        let (u, v) = utilities::orthonormal_basis(&w);
+        phi.cos() * sin_theta * u + phi.sin() * sin_theta * v + z * w
+    }
+
     fn get_uv(p: &Vec3) -> Vec2 {
         // p: a given point on the sphere of radius one, centered at the origin.
         // u: returned value [0,1] of angle around the Y axis from X=-1.
@@ -75,4 +129,21 @@ impl Sphere {
 
         Vec2::new(phi / (2.0 * PI), theta / PI)
     }
+
+    // Derivatives of the point with respect to the `get_uv` coordinates, at `p`
+    // on the unit sphere. There's no telling which way u goes at the poles, so
+    // both are left at zero there.
+    fn get_tangents(&self, p: &Vec3) -> (Vec3, Vec3) {
+        use std::f32::consts::PI;
+
+        let sin_theta = (p.x * p.x + p.z * p.z).sqrt();
+        if sin_theta < 1e-6 {
+            return (Vec3::zero(), Vec3::zero());
+        }
+
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv =
+            PI * self.radius * Vec3::new(-p.y * p.x / sin_theta, sin_theta, -p.y * p.z / sin_theta);
+        (dpdu, dpdv)
+    }
 }
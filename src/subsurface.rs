@@ -0,0 +1,86 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    material::{BsdfSample, Material},
+    medium::Medium,
+    microfacet,
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+    utilities::Frame,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::{any::Any, sync::Arc};
+
+// Translucent materials like wax, marble or skin: a smooth dielectric boundary
+// around a medium that light wanders through before coming back out, often some
+// distance from where it went in. Only makes sense on closed shapes.
+pub struct Subsurface {
+    pub ir: f32, // Index of Refraction
+    // Color the inside takes on, read where light goes in.
+    pub albedo: Arc<Texture>,
+    // Average distance light goes between scattering events, for each channel.
+    // Longer ones let that color through deeper.
+    pub mean_free_path: Color,
+    pub anisotropy: f32,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Arc<Texture>, mean_free_path: Color) -> Self {
+        Self {
+            ir: 1.5,
+            albedo,
+            mean_free_path,
+            anisotropy: 0.0,
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let frame = Frame::new(&rec.normal);
+        let wo = frame.to_local(&-r_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let eta = if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        };
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let f = microfacet::fresnel_dielectric(wo.z, eta);
+        let wi = match microfacet::refract(&wo, &n, eta) {
+            Some(wi) if sampler.random_float() >= f => wi,
+            _ => microfacet::reflect(&wo, &n),
+        };
+
+        Some(BsdfSample {
+            direction: frame.to_world(&wi),
+            weight: Color::white(),
+            pdf: 1.0,
+            is_specular: true,
+        })
+    }
+
+    fn emitted(&self, _uv: &Vec2, _p: &Vec3) -> Color {
+        Color::black()
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn medium(&self, rec: &HitRecord) -> Option<Medium> {
+        Some(Medium::new(
+            self.albedo.value_at(rec),
+            self.mean_free_path,
+            self.anisotropy,
+        ))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
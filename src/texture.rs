@@ -1,16 +1,23 @@
 use crate::{
     color::Color,
+    hittable::HitRecord,
     perlin::{self, Perlin},
 };
 use cliffy::{Vec2, Vec3};
 use image::{DynamicImage, GenericImageView};
-use std::rc::Rc;
+use std::{path::PathBuf, sync::Arc};
 
 pub enum Texture {
     SolidColor(Color),
-    Checker(f32, Rc<Texture>, Rc<Texture>),
+    Checker(f32, Arc<Texture>, Arc<Texture>),
+    // Another texture multiplied by a color, channel by channel.
+    Scaled(Color, Arc<Texture>),
     Noise(f32, Perlin),
-    Image(DynamicImage),
+    // The path the image was loaded from, if any, so the scene can be saved again.
+    Image(DynamicImage, Option<PathBuf>),
+    // The colors of the vertices of a mesh. Only meaningful through `value_at`,
+    // elsewhere it is plain white.
+    VertexColor,
 }
 
 impl Texture {
@@ -18,18 +25,18 @@ impl Texture {
         match self {
             Self::SolidColor(c) => *c,
             Self::Checker(size, even, odd) => {
-                let sines = (size * p.x).sin() * (size * p.y).sin() * (size * p.z).sin();
-                if sines < 0.0 {
+                if checker_is_odd(*size, p) {
                     odd.value(uv, p)
                 } else {
                     even.value(uv, p)
                 }
             }
+            Self::Scaled(factor, texture) => *factor * texture.value(uv, p),
             Self::Noise(scale, perlin) => {
                 // Color::white() * 0.5 * (1.0 + perlin.noise(&(*scale * *p)))
                 Color::white() * 0.5 * (1.0 + (scale * p.z + 10.0 * perlin.turb(p, 7)).sin())
             }
-            Self::Image(img) => {
+            Self::Image(img, _) => {
                 // Clamp input texture coordinates to [0,1] x [1,0]
                 let u = uv.x.clamp(0.0, 1.0);
                 let v = 1.0 - uv.y.clamp(0.0, 1.0); // Flip V to image coordinates
@@ -58,6 +65,28 @@ impl Texture {
 
                 Color::new(r, g, b)
             }
+            Self::VertexColor => Color::white(),
         }
     }
+
+    // Like `value`, but with the whole hit at hand, which vertex colors need.
+    pub fn value_at(&self, rec: &HitRecord) -> Color {
+        match self {
+            Self::VertexColor => rec.vertex_color.unwrap_or_else(Color::white),
+            Self::Checker(size, even, odd) => {
+                if checker_is_odd(*size, &rec.point) {
+                    odd.value_at(rec)
+                } else {
+                    even.value_at(rec)
+                }
+            }
+            Self::Scaled(factor, texture) => *factor * texture.value_at(rec),
+            _ => self.value(&rec.uv, &rec.point),
+        }
+    }
+}
+
+fn checker_is_odd(size: f32, p: &Vec3) -> bool {
+    let sines = (size * p.x).sin() * (size * p.y).sin() * (size * p.z).sin();
+    sines < 0.0
 }
@@ -0,0 +1,163 @@
+use crate::{color::Color, hittable::HitRecord, spectrum, texture::Texture};
+use std::{
+    f32::consts::PI,
+    ops::{Add, Div, Mul, Sub},
+    sync::Arc,
+};
+
+// Enough wavelengths to follow the interference of films up to a micrometer or
+// so thick without the colors breaking up.
+const SAMPLES: usize = 32;
+
+// A transparent film, like soap or oil or the oxide on anodized metal, a few
+// hundred nanometers thick. Light reflecting off its top and off what's under it
+// interferes, strengthening some wavelengths and cancelling others depending on
+// the angle and thickness, which is what makes it iridescent.
+pub struct ThinFilm {
+    // In micrometers, the average of the channels where a ray hits, which puts
+    // textures that range from 0 to 1 right where the colors are.
+    pub thickness: Arc<Texture>,
+    pub ir: f32, // Index of Refraction
+}
+
+impl ThinFilm {
+    pub fn new(thickness: Arc<Texture>, ir: f32) -> Self {
+        Self { thickness, ir }
+    }
+
+    // Fraction of light reflected at `rec`, coming in at the angle with cosine
+    // `cos_i` from a medium with index of refraction `outside`, off the film over
+    // a base with the complex index `eta + ik`, given for each channel. Just
+    // that of `wavelength`, as a gray, if the ray carries one.
+    pub fn reflectance(
+        &self,
+        rec: &HitRecord,
+        wavelength: Option<f32>,
+        cos_i: f32,
+        outside: f32,
+        eta: &Color,
+        k: &Color,
+    ) -> Color {
+        let t = self.thickness.value_at(rec);
+        let thickness = ((t.r + t.g + t.b) / 3.0).max(0.0) * 1000.0;
+        let cos_i = cos_i.clamp(0.0, 1.0);
+        let at = |lambda| {
+            let base = Complex::new(
+                spectrum::channel_at(eta, lambda),
+                spectrum::channel_at(k, lambda),
+            );
+            airy(cos_i, outside, self.ir, base, thickness, lambda)
+        };
+
+        if let Some(lambda) = wavelength {
+            let r = at(lambda);
+            return Color::new(r, r, r);
+        }
+
+        let rgb = spectrum::reflectance_to_rgb(SAMPLES, at);
+
+        // Saturated interference colors can fall outside what RGB can show.
+        Color::new(
+            rgb.r.clamp(0.0, 1.0),
+            rgb.g.clamp(0.0, 1.0),
+            rgb.b.clamp(0.0, 1.0),
+        )
+    }
+}
+
+// Reflectance of unpolarized light of wavelength `lambda` off a film of index
+// `n2` and `thickness`, both in nanometers, between indices `n1` and `n3`,
+// summing up all the reflections back and forth inside it.
+fn airy(cos1: f32, n1: f32, n2: f32, n3: Complex, thickness: f32, lambda: f32) -> f32 {
+    let one = Complex::new(1.0, 0.0);
+    let n1 = Complex::new(n1, 0.0);
+    let n2 = Complex::new(n2, 0.0);
+    let cos1 = Complex::new(cos1, 0.0);
+
+    // Snell's law, allowing for the angles to be complex inside absorbing or
+    // totally reflecting layers.
+    let sin2 = one - cos1 * cos1;
+    let cos2 = (one - sin2 * (n1 * n1) / (n2 * n2)).sqrt();
+    let cos3 = (one - sin2 * (n1 * n1) / (n3 * n3)).sqrt();
+
+    // Phase the light picks up going down through the film and back up.
+    let delta = Complex::new(0.0, 4.0 * PI * thickness / lambda) * n2 * cos2;
+    let phase = delta.exp();
+
+    let r12s = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let r23s = (n2 * cos2 - n3 * cos3) / (n2 * cos2 + n3 * cos3);
+    let r12p = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+    let r23p = (n3 * cos2 - n2 * cos3) / (n3 * cos2 + n2 * cos3);
+
+    let rs = (r12s + r23s * phase) / (one + r12s * r23s * phase);
+    let rp = (r12p + r23p * phase) / (one + r12p * r23p * phase);
+    (0.5 * (rs.norm_sqr() + rp.norm_sqr())).clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // The root with a positive real part.
+    fn sqrt(self) -> Self {
+        let r = self.norm_sqr().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Self::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn exp(self) -> Self {
+        let m = self.re.exp();
+        Self::new(m * self.im.cos(), m * self.im.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Complex {
+        let d = rhs.norm_sqr();
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / d,
+            (self.im * rhs.re - self.re * rhs.im) / d,
+        )
+    }
+}
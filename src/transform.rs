@@ -0,0 +1,74 @@
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    mat4::Mat4,
+    ray::Ray,
+    sampler::Sampler,
+};
+use cliffy::Vector;
+use std::sync::Arc;
+
+// Puts an object through any affine transform: rotation around any axis,
+// non-uniform scaling, shearing or a matrix taken from a file.
+pub struct Transform {
+    pub ptr: Arc<Hittable>,
+    pub matrix: Mat4,
+    pub inverse: Mat4,
+    pub bbox: Option<AABB>,
+}
+
+impl Transform {
+    pub fn new(ptr: Arc<Hittable>, matrix: Mat4) -> Self {
+        // A transform of a transform is a single one with both matrices.
+        if let Hittable::Transform(inner) = &*ptr {
+            return Self::new(inner.ptr.clone(), matrix * inner.matrix);
+        }
+
+        let inverse = matrix.inverse().expect("a transform has to be invertible");
+        let bbox = ptr
+            .bounding_box(0.0, 1.0)
+            .map(|bbox| bbox.transformed(&matrix));
+
+        Self {
+            ptr,
+            matrix,
+            inverse,
+            bbox,
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        let local_r = ray_to_local(&self.inverse, r);
+
+        if let Some(mut rec) = self.ptr.hit(&local_r, t_min, t_max, sampler) {
+            hit_to_world(&self.matrix, &self.inverse, &mut rec);
+            Some(rec)
+        } else {
+            None
+        }
+    }
+
+    pub fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        self.bbox.clone()
+    }
+}
+
+// Takes a ray into the space of a transformed object. The direction is not
+// normalized again, so `t` means the same on both sides of the transform.
+pub fn ray_to_local(inverse: &Mat4, r: &Ray) -> Ray {
+    Ray::with_time(
+        inverse.transform_point(&r.origin),
+        inverse.transform_vector(&r.direction),
+        r.time,
+    )
+}
+
+// Brings a hit found in the object's space back out. Which side was hit stays
+// the same, even if the transform mirrors.
+pub fn hit_to_world(matrix: &Mat4, inverse: &Mat4, rec: &mut HitRecord) {
+    rec.point = matrix.transform_point(&rec.point);
+    rec.normal = inverse.transform_normal(&rec.normal).normalized();
+    rec.geometric_normal = inverse.transform_normal(&rec.geometric_normal).normalized();
+    rec.dpdu = matrix.transform_vector(&rec.dpdu);
+    rec.dpdv = matrix.transform_vector(&rec.dpdv);
+}
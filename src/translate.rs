@@ -2,27 +2,30 @@ use crate::{
     aabb::AABB,
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    sampler::Sampler,
 };
 use cliffy::Vec3;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct Translate {
-    ptr: Rc<Hittable>,
-    offset: Vec3,
+    pub ptr: Arc<Hittable>,
+    pub offset: Vec3,
 }
 
 impl Translate {
-    pub fn new(ptr: Rc<Hittable>, offset: Vec3) -> Self {
+    pub fn new(ptr: Arc<Hittable>, offset: Vec3) -> Self {
         Self { ptr, offset }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
         let moved_r = Ray::with_time(r.origin - self.offset, r.direction, r.time);
 
-        if let Some(mut rec) = self.ptr.hit(&moved_r, t_min, t_max) {
+        if let Some(mut rec) = self.ptr.hit(&moved_r, t_min, t_max, sampler) {
             rec.point += self.offset;
             let rec_normal = rec.normal;
+            let geometric_normal = rec.geometric_normal;
             rec.set_face_normal(&moved_r, &rec_normal);
+            rec.geometric_normal = geometric_normal;
 
             return Some(rec);
         } else {
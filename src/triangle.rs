@@ -0,0 +1,285 @@
+use crate::{
+    aabb::AABB,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    linear_bvh::LinearBVH,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+};
+use cliffy::{Vec2, Vec3, Vector};
+use std::sync::Arc;
+
+// Vertex attributes shared by all the triangles of a mesh. `normals`, `uvs` and
+// `colors` are either empty or hold one entry per position.
+pub struct MeshVertices {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Color>,
+}
+
+impl MeshVertices {
+    pub fn new(positions: Vec<Vec3>, normals: Vec<Vec3>, uvs: Vec<Vec2>) -> Self {
+        Self::with_colors(positions, normals, uvs, Vec::new())
+    }
+
+    pub fn with_colors(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<Vec2>,
+        colors: Vec<Color>,
+    ) -> Self {
+        assert!(normals.is_empty() || normals.len() == positions.len());
+        assert!(uvs.is_empty() || uvs.len() == positions.len());
+        assert!(colors.is_empty() || colors.len() == positions.len());
+
+        Self {
+            positions,
+            normals,
+            uvs,
+            colors,
+        }
+    }
+}
+
+pub struct Triangle {
+    pub vertices: Arc<MeshVertices>,
+    pub indices: [usize; 3],
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(
+        vertices: Arc<MeshVertices>,
+        indices: [usize; 3],
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(indices.iter().all(|&i| i < vertices.positions.len()));
+
+        Self {
+            vertices,
+            indices,
+            material,
+        }
+    }
+
+    pub fn hit(
+        &self,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _sampler: &mut Sampler,
+    ) -> Option<HitRecord> {
+        let [i0, i1, i2] = self.indices;
+        let p0 = self.vertices.positions[i0];
+        let p1 = self.vertices.positions[i1];
+        let p2 = self.vertices.positions[i2];
+
+        // Watertight ray/triangle intersection (Woop, Benthin and Wald 2013): the
+        // vertices are moved into a space where the ray starts at the origin and
+        // points down +Z, so the edge tests of two triangles sharing an edge are
+        // computed from exactly the same numbers and no ray slips between them.
+        let kz = max_dimension(&Vec3::new(
+            r.direction.x.abs(),
+            r.direction.y.abs(),
+            r.direction.z.abs(),
+        ));
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+        let d = permute(&r.direction, kx, ky, kz);
+        let mut p0t = permute(&(p0 - r.origin), kx, ky, kz);
+        let mut p1t = permute(&(p1 - r.origin), kx, ky, kz);
+        let mut p2t = permute(&(p2 - r.origin), kx, ky, kz);
+
+        let sx = -d.x / d.z;
+        let sy = -d.y / d.z;
+        let sz = 1.0 / d.z;
+        p0t.x += sx * p0t.z;
+        p0t.y += sy * p0t.z;
+        p1t.x += sx * p1t.z;
+        p1t.y += sy * p1t.z;
+        p2t.x += sx * p2t.z;
+        p2t.y += sy * p2t.z;
+
+        let mut e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+        let mut e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+        let mut e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+
+        // An edge function of exactly zero may be a rounding artifact, so redo
+        // those in double precision before deciding which side the ray is on.
+        if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+            e0 = (p1t.x as f64 * p2t.y as f64 - p1t.y as f64 * p2t.x as f64) as f32;
+            e1 = (p2t.x as f64 * p0t.y as f64 - p2t.y as f64 * p0t.x as f64) as f32;
+            e2 = (p0t.x as f64 * p1t.y as f64 - p0t.y as f64 * p1t.x as f64) as f32;
+        }
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None;
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return None;
+        }
+
+        // Compare the scaled distance against the range before dividing.
+        p0t.z *= sz;
+        p1t.z *= sz;
+        p2t.z *= sz;
+        let t_scaled = e0 * p0t.z + e1 * p1t.z + e2 * p2t.z;
+        if det < 0.0 && (t_scaled >= t_min * det || t_scaled < t_max * det) {
+            return None;
+        }
+        if det > 0.0 && (t_scaled <= t_min * det || t_scaled > t_max * det) {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let b0 = e0 * inv_det;
+        let b1 = e1 * inv_det;
+        let b2 = e2 * inv_det;
+
+        let mut rec = HitRecord::with_mat_only(self.material.clone());
+        rec.t = t_scaled * inv_det;
+        rec.point = b0 * p0 + b1 * p1 + b2 * p2;
+
+        let [uv0, uv1, uv2] = if self.vertices.uvs.is_empty() {
+            [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+            ]
+        } else {
+            let uvs = &self.vertices.uvs;
+            [uvs[i0], uvs[i1], uvs[i2]]
+        };
+        rec.uv = Vec2::new(
+            b0 * uv0.x + b1 * uv1.x + b2 * uv2.x,
+            b0 * uv0.y + b1 * uv1.y + b2 * uv2.y,
+        );
+
+        // Texture coordinates that all fall on a line leave the tangents at zero.
+        let (du02, dv02) = (uv0.x - uv2.x, uv0.y - uv2.y);
+        let (du12, dv12) = (uv1.x - uv2.x, uv1.y - uv2.y);
+        let uv_det = du02 * dv12 - dv02 * du12;
+        if uv_det.abs() > 1e-9 {
+            let (dp02, dp12) = (p0 - p2, p1 - p2);
+            rec.dpdu = (dv12 * dp02 - dv02 * dp12) / uv_det;
+            rec.dpdv = (du02 * dp12 - du12 * dp02) / uv_det;
+        }
+
+        if !self.vertices.colors.is_empty() {
+            let colors = &self.vertices.colors;
+            rec.vertex_color = Some(b0 * colors[i0] + b1 * colors[i1] + b2 * colors[i2]);
+        }
+
+        // The winding order decides which side is outside, unless the mesh has
+        // vertex normals, in which case the geometric normal is flipped to agree
+        // with them.
+        let mut outward_normal = (p1 - p0).cross(p2 - p0).normalized();
+        let mut shading_normal = None;
+        if !self.vertices.normals.is_empty() {
+            let normals = &self.vertices.normals;
+            let n = b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2];
+            if n.mag_sq() > 0.0 {
+                let n = n.normalized();
+                if n.dot(outward_normal) < 0.0 {
+                    outward_normal = -outward_normal;
+                }
+                shading_normal = Some(n);
+            }
+        }
+
+        rec.set_face_normal(r, &outward_normal);
+        if let Some(n) = shading_normal {
+            rec.normal = if rec.front_face { n } else { -n };
+        }
+
+        Some(rec)
+    }
+
+    pub fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<AABB> {
+        let [i0, i1, i2] = self.indices;
+        let p0 = self.vertices.positions[i0];
+        let p1 = self.vertices.positions[i1];
+        let p2 = self.vertices.positions[i2];
+
+        // Pad every dimension a small amount, so triangles lying in an
+        // axis-aligned plane still get a box with some thickness.
+        let mut min = Vec3::zero();
+        let mut max = Vec3::zero();
+        for a in 0..3 {
+            min[a] = p0[a].min(p1[a]).min(p2[a]) - 0.0001;
+            max[a] = p0[a].max(p1[a]).max(p2[a]) + 0.0001;
+        }
+
+        Some(AABB::new(&min, &max))
+    }
+}
+
+// Many triangles sharing one set of vertex buffers, with a BVH over them.
+pub struct TriangleMesh {
+    pub vertices: Arc<MeshVertices>,
+    pub indices: Vec<[usize; 3]>,
+    pub material: Arc<dyn Material>,
+    pub bvh: LinearBVH,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        vertices: Arc<MeshVertices>,
+        indices: Vec<[usize; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            !indices.is_empty(),
+            "a triangle mesh needs at least one triangle"
+        );
+
+        let mut triangles = HittableList::empty();
+        for face in &indices {
+            triangles.add(Arc::new(Hittable::Triangle(Triangle::new(
+                vertices.clone(),
+                *face,
+                material.clone(),
+            ))));
+        }
+        let bvh = LinearBVH::new(&mut triangles, 0.0, 1.0);
+
+        Self {
+            vertices,
+            indices,
+            material,
+            bvh,
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, t_min: f32, t_max: f32, sampler: &mut Sampler) -> Option<HitRecord> {
+        self.bvh.hit(r, t_min, t_max, sampler)
+    }
+
+    pub fn bounding_box(&self, time0: f32, time1: f32) -> Option<AABB> {
+        self.bvh.bounding_box(time0, time1)
+    }
+}
+
+#[inline]
+fn max_dimension(v: &Vec3) -> usize {
+    if v.x > v.y {
+        if v.x > v.z {
+            0
+        } else {
+            2
+        }
+    } else if v.y > v.z {
+        1
+    } else {
+        2
+    }
+}
+
+#[inline]
+fn permute(v: &Vec3, x: usize, y: usize, z: usize) -> Vec3 {
+    Vec3::new(v[x], v[y], v[z])
+}